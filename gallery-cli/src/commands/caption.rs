@@ -0,0 +1,30 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, image_id: String, text: String, s3_args: S3Args) -> Result<()> {
+    // An empty caption clears the existing one
+    let text = text.trim();
+    let caption = (!text.is_empty()).then(|| text.to_string());
+
+    tracing::info!("Setting caption for image {} in album {}", image_id, album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let Some(image) = manifest.image_mut(&image_id) else {
+        anyhow::bail!("Image not found in album {album_id}: {image_id}");
+    };
+    image.caption = caption;
+    manifest.update(&s3).await?;
+
+    if text.is_empty() {
+        println!("✓ Caption cleared for {image_id}");
+    } else {
+        println!("✓ Caption set for {image_id}: {text}");
+    }
+
+    Ok(())
+}
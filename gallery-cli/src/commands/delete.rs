@@ -1,11 +1,11 @@
 use anyhow::Result;
-use gallery_core::S3Client;
+use super::S3Args;
 
-pub async fn execute(album_id: String, bucket: String) -> Result<()> {
+pub async fn execute(album_id: String, s3_args: S3Args) -> Result<()> {
     tracing::info!("Deleting album: {}", album_id);
 
     // Initialize S3 client
-    let s3 = S3Client::new(bucket).await?;
+    let s3 = s3_args.connect().await?;
 
     // Check if manifest exists
     let manifest_key = format!("{album_id}/manifest.json");
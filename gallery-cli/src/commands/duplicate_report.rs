@@ -0,0 +1,98 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::upload::OutputFormat;
+use super::{format_bytes, S3Args};
+
+/// One image found in more than one album
+#[derive(Serialize)]
+struct DuplicateGroup {
+    file_hash: String,
+    /// Size of one copy of the original
+    size_bytes: u64,
+    copies: Vec<AlbumCopy>,
+}
+
+#[derive(Serialize)]
+struct AlbumCopy {
+    album_id: String,
+    image_id: String,
+    original_filename: String,
+}
+
+/// Summary printed with `--output json`
+#[derive(Serialize)]
+struct DuplicateReport {
+    albums_scanned: usize,
+    groups: Vec<DuplicateGroup>,
+    /// Original bytes freed by keeping a single copy of every group
+    reclaimable_bytes: u64,
+}
+
+pub async fn execute(output: OutputFormat, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Scanning albums for duplicate images");
+
+    let s3 = s3_args.connect().await?;
+    let album_ids = s3.list_albums().await?;
+
+    // Group every image in the bucket by its content hash
+    let mut by_hash: BTreeMap<String, DuplicateGroup> = BTreeMap::new();
+    let mut albums_scanned = 0;
+    for album_id in &album_ids {
+        let manifest = match AlbumManifest::fetch(&s3, album_id).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                tracing::warn!("Skipping {}: {:#}", album_id, e);
+                continue;
+            }
+        };
+        albums_scanned += 1;
+
+        for image in manifest.images {
+            let group = by_hash.entry(image.file_hash.clone()).or_insert_with(|| DuplicateGroup {
+                file_hash: image.file_hash.clone(),
+                size_bytes: 0,
+                copies: Vec::new(),
+            });
+            group.size_bytes = group.size_bytes.max(image.original_size_bytes.unwrap_or(0));
+            group.copies.push(AlbumCopy {
+                album_id: album_id.clone(),
+                image_id: image.id,
+                original_filename: image.original_filename,
+            });
+        }
+    }
+
+    let groups: Vec<DuplicateGroup> = by_hash.into_values().filter(|group| group.copies.len() > 1).collect();
+    let reclaimable_bytes = groups
+        .iter()
+        .map(|group| group.size_bytes * (group.copies.len() as u64 - 1))
+        .sum();
+
+    if output == OutputFormat::Json {
+        let report = DuplicateReport { albums_scanned, groups, reclaimable_bytes };
+        println!("{}", serde_json::to_string(&report)?);
+        return Ok(());
+    }
+
+    for group in &groups {
+        println!("{} ({} copies, {})", group.file_hash, group.copies.len(), format_bytes(group.size_bytes));
+        for copy in &group.copies {
+            println!("  - {}/{} ({})", copy.album_id, copy.image_id, copy.original_filename);
+        }
+    }
+
+    if groups.is_empty() {
+        println!("✓ No duplicate images across {albums_scanned} albums");
+    } else {
+        println!(
+            "\n{} image(s) appear in more than one album; keeping one copy of each would free {}",
+            groups.len(),
+            format_bytes(reclaimable_bytes)
+        );
+    }
+
+    Ok(())
+}
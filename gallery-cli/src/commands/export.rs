@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use gallery_core::{AlbumManifest, ImageInfo, Tier};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{progress_bar, S3Args};
+
+pub async fn execute(album_id: String, dest_dir: PathBuf, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Exporting album {} to {}", album_id, dest_dir.display());
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+
+    fs::create_dir_all(&dest_dir)
+        .with_context(|| format!("Failed to create {}", dest_dir.display()))?;
+
+    let pb = progress_bar(true, manifest.images.len(), "[{elapsed_precise}] {bar:40.green/blue} {pos}/{len} {msg}");
+
+    let mut used_names = HashSet::new();
+    let mut exported = 0;
+    let mut skipped = 0;
+    let mut mismatched = Vec::new();
+    for image in &manifest.images {
+        if !image.has_tier(Tier::Original) {
+            pb.println(format!("⚠ Skipping {}: its original hasn't been uploaded", image.original_filename));
+            pb.inc(1);
+            continue;
+        }
+
+        let filename = unique_filename(&image.original_filename, &mut used_names);
+        let path = dest_dir.join(&filename);
+
+        // An earlier, interrupted export may already have fetched this one
+        if already_downloaded(&path, image) {
+            pb.println(format!("= {filename} skipped (already downloaded)"));
+            skipped += 1;
+            pb.inc(1);
+            continue;
+        }
+
+        pb.set_message(format!("Downloading: {filename}"));
+
+        let data = s3.download_file(&format!("{album_id}/{}", image.original_path)).await?;
+
+        // Re-encoded originals never matched the source hash, so there's nothing to check
+        if !image.original_transcoded {
+            let hash = format!("{:x}", Sha256::digest(&data));
+            if !hash.eq_ignore_ascii_case(&image.file_hash) {
+                pb.println(format!("✗ {filename} doesn't match its recorded SHA-256; it may be corrupted"));
+                mismatched.push(filename.clone());
+            }
+        }
+
+        // Write under a temporary name so an interrupted export never leaves a truncated original behind
+        let partial_path = dest_dir.join(format!("{filename}.part"));
+        fs::write(&partial_path, data).with_context(|| format!("Failed to write {}", partial_path.display()))?;
+        fs::rename(&partial_path, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        exported += 1;
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Download complete");
+
+    // Keep the manifest alongside the originals so the export is self-describing
+    let manifest_path = dest_dir.join("manifest.json");
+    fs::write(&manifest_path, manifest.to_json()?)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    println!("\n✓ Exported {exported} images from album {album_id}");
+    if skipped > 0 {
+        println!("  {skipped} already downloaded and skipped");
+    }
+    println!("Destination: {}", dest_dir.display());
+
+    if !mismatched.is_empty() {
+        anyhow::bail!(
+            "{} exported original(s) don't match their upload hash: {}",
+            mismatched.len(),
+            mismatched.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether `path` already holds this image's original: same SHA-256 as uploaded, or for
+/// re-encoded originals (which have no source hash to compare with) the same size
+fn already_downloaded(path: &Path, image: &ImageInfo) -> bool {
+    if image.original_transcoded {
+        let local_size = fs::metadata(path).map(|metadata| metadata.len()).ok();
+        return local_size.is_some() && local_size == image.original_size_bytes;
+    }
+
+    match fs::read(path) {
+        Ok(data) => format!("{:x}", Sha256::digest(&data)).eq_ignore_ascii_case(&image.file_hash),
+        Err(_) => false,
+    }
+}
+
+/// The original filename, made safe for the local filesystem and suffixed
+/// (`photo-1.jpg`, `photo-2.jpg`, ...) when an earlier image already took it
+fn unique_filename(original_filename: &str, used_names: &mut HashSet<String>) -> String {
+    // Never let a manifest entry escape the destination directory
+    let name = Path::new(original_filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| name != "manifest.json")
+        .unwrap_or_else(|| "image.jpg".to_string());
+
+    let path = Path::new(&name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+
+    let mut candidate = name.clone();
+    let mut suffix = 1;
+    // Compare case-insensitively so exports stay intact on macOS/Windows filesystems
+    while !used_names.insert(candidate.to_lowercase()) {
+        candidate = format!("{stem}-{suffix}{extension}");
+        suffix += 1;
+    }
+
+    candidate
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use gallery_core::{AlbumManifest, DateTime};
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, days: u32, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Extending expiry of album {} by {} days", album_id, days);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let Some(current) = manifest.expires_at.as_deref() else {
+        anyhow::bail!("Album {album_id} has no expiry; it is already kept forever");
+    };
+    let current = chrono::DateTime::parse_from_rfc3339(current)
+        .map_err(|e| anyhow::anyhow!("Album {album_id} has an unreadable expiry {current:?}: {e}"))?
+        .with_timezone(&Utc);
+
+    // Count from the current expiry, or from now if it has already passed
+    let expires_at = current.max(Utc::now()) + Duration::days(i64::from(days));
+
+    // Same offsets as `upload --expires-in-days`: images outlive the manifest by an hour
+    let image_expires = DateTime::from_secs((expires_at + Duration::hours(1)).timestamp());
+    let manifest_expires = DateTime::from_secs(expires_at.timestamp());
+
+    let keys: Vec<String> = manifest
+        .images
+        .iter()
+        .flat_map(|image| image.object_paths())
+        .map(|path| format!("{album_id}/{path}"))
+        .collect();
+    for key in &keys {
+        s3.set_expires(key, Some(image_expires)).await?;
+    }
+
+    // Rewrite the manifest last, keeping its storage class and encryption
+    let manifest_key = AlbumManifest::key(&album_id);
+    let mut options = s3.upload_options(&manifest_key).await?;
+    options.expires = Some(manifest_expires);
+    manifest.expires_at = Some(expires_at.to_rfc3339());
+    manifest.store_with(&s3, &options).await?;
+
+    println!("✓ Album {album_id} now expires at {}", expires_at.format("%Y-%m-%d %H:%M UTC"));
+    println!("  Updated expiry of {} object(s)", keys.len() + 1);
+
+    Ok(())
+}
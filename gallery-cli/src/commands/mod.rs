@@ -1,2 +1,173 @@
+use anyhow::Result;
+use clap::Args;
+use gallery_core::{ClientOptions, S3Client};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::image_processor::{ResizeFilter, PREVIEW_QUALITY, THUMBNAIL_QUALITY};
+use crate::watermark::{Watermark, WatermarkPosition};
+
 pub mod upload;
 pub mod delete;
+pub mod remove;
+pub mod rename;
+pub mod verify;
+pub mod caption;
+pub mod set_cover;
+pub mod reorder;
+pub mod tag;
+pub mod export;
+pub mod static_export;
+pub mod reprocess;
+pub mod setup_lifecycle;
+pub mod extend;
+pub mod duplicate_report;
+pub mod prune_orphans;
+pub mod refresh_urls;
+pub mod sign_url;
+pub mod stats;
+
+/// S3 connection flags shared by every command
+#[derive(Args)]
+pub struct S3Args {
+    /// S3 bucket name
+    #[arg(short, long, env = "GALLERY_BUCKET")]
+    pub bucket: Option<String>,
+
+    /// Separate bucket for full-size originals; thumbnails, previews and manifests stay in --bucket
+    #[arg(long, env = "GALLERY_ORIGINALS_BUCKET")]
+    pub originals_bucket: Option<String>,
+
+    /// Namespace for albums within the bucket: keys become `{prefix}/{album-id}/...`.
+    /// Leave unset (or empty) for albums stored at the bucket root
+    #[arg(long, env = "GALLERY_PREFIX")]
+    pub prefix: Option<String>,
+
+    /// AWS region of the bucket (overrides GALLERY_REGION and AWS_REGION)
+    #[arg(long)]
+    pub region: Option<String>,
+
+    /// Custom S3 endpoint, e.g. for MinIO (overrides AWS_ENDPOINT_URL)
+    #[arg(long)]
+    pub endpoint_url: Option<String>,
+
+    /// Named AWS profile to take credentials from (default: the standard credential chain)
+    #[arg(long, env = "AWS_PROFILE")]
+    pub profile: Option<String>,
+}
+
+impl S3Args {
+    /// Fill in anything not given by a flag or environment variable from the config file
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.bucket = self.bucket.or_else(|| config.bucket.clone());
+        self.originals_bucket = self.originals_bucket.or_else(|| config.originals_bucket.clone());
+        self.prefix = self.prefix.or_else(|| config.prefix.clone());
+        self.region = self
+            .region
+            .or_else(|| std::env::var("GALLERY_REGION").ok())
+            .or_else(|| config.region.clone());
+        self.endpoint_url = self
+            .endpoint_url
+            .or_else(|| std::env::var("AWS_ENDPOINT_URL").ok())
+            .or_else(|| config.endpoint_url.clone());
+        self.profile = self.profile.or_else(|| config.profile.clone());
+        self
+    }
+
+    pub async fn connect(self) -> Result<S3Client> {
+        let Some(bucket) = self.bucket else {
+            anyhow::bail!("No bucket given: pass --bucket, set GALLERY_BUCKET, or add `bucket` to gallery.toml");
+        };
+        let options = ClientOptions {
+            region: self.region,
+            endpoint_url: self.endpoint_url,
+            force_path_style: None,
+            profile: self.profile,
+            originals_bucket: self.originals_bucket,
+            key_prefix: self.prefix,
+        };
+        S3Client::new_with_options(bucket, options).await
+    }
+}
+
+/// Watermark flags shared by commands that generate previews
+#[derive(Args)]
+pub struct WatermarkArgs {
+    /// PNG overlaid onto previews; thumbnails stay unmarked
+    #[arg(long)]
+    pub watermark: Option<PathBuf>,
+
+    /// Where the watermark sits on the image
+    #[arg(long, value_enum, default_value_t = WatermarkPosition::BottomRight, requires = "watermark")]
+    pub watermark_position: WatermarkPosition,
+
+    /// Watermark opacity from 0.0 (invisible) to 1.0 (as drawn)
+    #[arg(long, default_value_t = 0.5, value_parser = parse_opacity, requires = "watermark")]
+    pub watermark_opacity: f32,
+}
+
+impl WatermarkArgs {
+    /// Load the watermark image, if one was given
+    pub fn load(&self, apply_to_original: bool) -> Result<Option<Arc<Watermark>>> {
+        self.watermark
+            .as_deref()
+            .map(|path| {
+                Watermark::load(path, self.watermark_position, self.watermark_opacity, apply_to_original).map(Arc::new)
+            })
+            .transpose()
+    }
+}
+
+/// Quality flags shared by commands that generate previews and thumbnails
+#[derive(Args)]
+pub struct QualityArgs {
+    /// JPEG quality of grid thumbnails, 1-100
+    #[arg(long, default_value_t = THUMBNAIL_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub thumbnail_quality: u8,
+
+    /// JPEG quality of lightbox previews (and their responsive variants), 1-100
+    #[arg(long, default_value_t = PREVIEW_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub preview_quality: u8,
+
+    /// Resampling filter for downscaling; `triangle` is much faster on big batches
+    #[arg(long, value_enum, default_value_t = ResizeFilter::Lanczos3)]
+    pub resize_filter: ResizeFilter,
+}
+
+/// Progress bar in the style shared by every command
+pub fn progress_bar(visible: bool, len: usize, template: &str) -> ProgressBar {
+    if !visible {
+        return ProgressBar::hidden();
+    }
+
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(template)
+            .expect("Invalid progress bar template")
+            .progress_chars("█▓▒░ "),
+    );
+    pb
+}
+
+/// Object sizes as the commands print them: KB (rounded up), MB or GB in binary units
+pub fn format_bytes(bytes: u64) -> String {
+    const MB: u64 = 1024 * 1024;
+    const GB: u64 = 1024 * MB;
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else {
+        format!("{} KB", bytes.div_ceil(1024))
+    }
+}
+
+fn parse_opacity(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(opacity),
+        _ => Err(format!("expected an opacity between 0.0 and 1.0, got {value:?}")),
+    }
+}
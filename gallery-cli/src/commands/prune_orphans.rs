@@ -0,0 +1,63 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+use std::collections::HashSet;
+
+use super::{format_bytes, S3Args};
+
+pub async fn execute(album_id: String, dry_run: bool, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Looking for orphaned objects in album {}", album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let manifest_key = AlbumManifest::key(&album_id);
+
+    // Paths recorded for tiers that are still pending count as referenced too
+    let referenced: HashSet<String> = manifest
+        .images
+        .iter()
+        .flat_map(|image| {
+            image
+                .object_paths()
+                .into_iter()
+                .chain([image.original_path.as_str(), image.preview_path.as_str(), image.thumbnail_path.as_str()])
+                .map(|path| format!("{album_id}/{path}"))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let orphans: Vec<(String, u64)> = s3
+        .list_objects(&format!("{album_id}/"))
+        .await?
+        .into_iter()
+        .filter(|(key, _)| *key != manifest_key && !referenced.contains(key))
+        .collect();
+
+    if orphans.is_empty() {
+        println!("✓ No orphaned objects in album {album_id}");
+        return Ok(());
+    }
+
+    let total_bytes: u64 = orphans.iter().map(|(_, size)| size).sum();
+    for (key, size) in &orphans {
+        if dry_run {
+            println!("  - {key} ({})", format_bytes(*size));
+        } else {
+            s3.delete_object(key).await?;
+            println!("✓ Deleted {key} ({})", format_bytes(*size));
+        }
+    }
+
+    if dry_run {
+        println!(
+            "\nDry run: {} orphaned object(s), {}, would be deleted",
+            orphans.len(),
+            format_bytes(total_bytes)
+        );
+    } else {
+        println!("\n✓ Deleted {} orphaned object(s), freeing {}", orphans.len(), format_bytes(total_bytes));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result};
+use gallery_core::{AlbumManifest, ImageInfo, S3Client, Tier};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, expires_days: u32, out: Option<PathBuf>, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Refreshing presigned URLs for album {}", album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let expires_in = Duration::from_secs(u64::from(expires_days) * 24 * 3600);
+
+    for image in &mut manifest.images {
+        // Tiers that aren't stored yet are signed as the larger tier shown in their place,
+        // or not at all when there is none
+        image.thumbnail_url = presign_tier(&s3, &album_id, image, Tier::Thumbnail, expires_in).await?;
+        image.preview_url = presign_tier(&s3, &album_id, image, Tier::Preview, expires_in).await?;
+        image.original_url = presign_tier(&s3, &album_id, image, Tier::Original, expires_in).await?;
+        for variant in &mut image.preview_variants {
+            variant.url = Some(s3.generate_presigned_url(&format!("{album_id}/{}", variant.path), expires_in).await?);
+        }
+    }
+
+    let valid_until = chrono::Utc::now() + chrono::Duration::days(i64::from(expires_days));
+
+    match out {
+        Some(path) => {
+            // The file is meant to be published, so leave the password hash out of it
+            manifest.password_hash = None;
+            fs::write(&path, manifest.to_json()?).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("✓ Wrote manifest with fresh URLs to {}", path.display());
+        }
+        None => {
+            manifest.update(&s3).await?;
+            println!("✓ Stored manifest with fresh URLs at {}", AlbumManifest::key(&album_id));
+        }
+    }
+    println!("  {} images; URLs valid until {}", manifest.images.len(), valid_until.format("%Y-%m-%d %H:%M UTC"));
+
+    Ok(())
+}
+
+async fn presign_tier(
+    s3: &S3Client,
+    album_id: &str,
+    image: &ImageInfo,
+    tier: Tier,
+    expires_in: Duration,
+) -> Result<Option<String>> {
+    match image.display_path(tier) {
+        Some(path) => Ok(Some(s3.generate_presigned_url(&format!("{album_id}/{path}"), expires_in).await?)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, image_id: String, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Removing image {} from album {}", image_id, album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    // Load the manifest and drop the image entry
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let Some(image) = manifest.remove_image(&image_id) else {
+        anyhow::bail!("Image not found in album {album_id}: {image_id}");
+    };
+
+    // Delete all tiers first; the manifest is only rewritten if every delete succeeds
+    for path in image.object_paths() {
+        s3.delete_object(&format!("{album_id}/{path}")).await?;
+    }
+
+    manifest.update(&s3).await?;
+
+    println!("✓ Removed {} ({image_id}) from album {album_id}", image.original_filename);
+    println!("Remaining images: {}", manifest.images.len());
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, new_name: String, s3_args: S3Args) -> Result<()> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        anyhow::bail!("Album name cannot be empty");
+    }
+
+    tracing::info!("Renaming album {} to {:?}", album_id, new_name);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let old_name = std::mem::replace(&mut manifest.name, new_name.to_string());
+    manifest.update(&s3).await?;
+
+    println!("✓ Album renamed: {old_name} → {new_name}");
+
+    Ok(())
+}
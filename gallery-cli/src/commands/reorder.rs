@@ -0,0 +1,46 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+use std::collections::{HashMap, HashSet};
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, order: Vec<String>, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Reordering images of album {}", album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+
+    // The new order must name every image exactly once
+    let mut seen = HashSet::new();
+    let duplicates: Vec<&str> = order.iter().filter(|id| !seen.insert(id.as_str())).map(String::as_str).collect();
+    if !duplicates.is_empty() {
+        anyhow::bail!("Image IDs listed more than once: {}", duplicates.join(", "));
+    }
+    let unknown: Vec<&str> = order
+        .iter()
+        .filter(|id| manifest.images.iter().all(|img| &img.id != *id))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        anyhow::bail!("Images not found in album {album_id}: {}", unknown.join(", "));
+    }
+    let left_out: Vec<&str> = manifest
+        .images
+        .iter()
+        .filter(|img| !seen.contains(img.id.as_str()))
+        .map(|img| img.id.as_str())
+        .collect();
+    if !left_out.is_empty() {
+        anyhow::bail!("--order must list every image; missing: {}", left_out.join(", "));
+    }
+
+    let position: HashMap<&str, usize> = order.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+    manifest.images.sort_by_key(|img| position[img.id.as_str()]);
+    manifest.update(&s3).await?;
+
+    println!("✓ Reordered {} images in album {album_id}", manifest.images.len());
+
+    Ok(())
+}
@@ -0,0 +1,312 @@
+use anyhow::Result;
+use clap::Args;
+use gallery_core::{AlbumManifest, DateTime, ImageInfo, PreviewVariant, S3Client, Tier, UploadOptions};
+use std::io::IsTerminal;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+use super::{progress_bar, QualityArgs, S3Args, WatermarkArgs};
+use crate::config::Config;
+use crate::image_processor::{process_original, ProcessOptions};
+
+/// Images reprocessed at once unless --concurrency or the config file says otherwise
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// AVIF encoder speed used when refreshing an album's existing AVIF tiers
+const AVIF_SPEED: u8 = 8;
+
+#[derive(Args)]
+pub struct ReprocessArgs {
+    /// Album ID to reprocess
+    pub album_id: String,
+
+    #[command(flatten)]
+    pub s3: S3Args,
+
+    /// Maximum number of images downloaded and regenerated at once (default: 4)
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub concurrency: Option<usize>,
+
+    /// Longest edge of grid thumbnails, in pixels (default: 400)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..))]
+    pub thumbnail_size: Option<u32>,
+
+    /// Longest edge of lightbox previews, in pixels (default: 2048)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..))]
+    pub preview_size: Option<u32>,
+
+    /// Tiers to regenerate, comma-separated from thumbnail and preview. Originals can't be rebuilt
+    /// from S3; run `upload --tiers original` over the same photos to fill those in
+    #[arg(long, value_delimiter = ',', default_value = "thumbnail,preview", value_parser = parse_derived_tier)]
+    pub tiers: Vec<Tier>,
+
+    #[command(flatten)]
+    pub quality: QualityArgs,
+
+    /// Watermark previews again; albums uploaded with --watermark lose it otherwise
+    #[command(flatten)]
+    pub watermark: WatermarkArgs,
+
+    /// Hide the progress bar
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+impl ReprocessArgs {
+    /// Fill in defaults from the config file for anything not given on the command line
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.s3 = self.s3.with_config(config);
+        self.concurrency = self.concurrency.or(config.concurrency);
+        self.thumbnail_size = self.thumbnail_size.or(config.thumbnail_size);
+        self.preview_size = self.preview_size.or(config.preview_size);
+        self
+    }
+}
+
+/// What changed for one image after regenerating its tiers
+struct Regenerated {
+    image_id: String,
+    width: u32,
+    height: u32,
+    lqip: String,
+    orientation: Option<u16>,
+    /// `None` when previews weren't regenerated and keep their current variants
+    preview_variants: Option<Vec<PreviewVariant>>,
+    /// Preview variants of sizes that are no longer generated, deleted once the manifest is stored
+    stale_paths: Vec<String>,
+}
+
+/// Tiers reprocess can regenerate; originals only ever come from `upload`
+fn parse_derived_tier(value: &str) -> Result<Tier, String> {
+    match value.parse::<Tier>()? {
+        Tier::Original => Err(
+            "originals can't be regenerated from S3; run `upload --tiers original` over the same photos instead"
+                .to_string(),
+        ),
+        tier => Ok(tier),
+    }
+}
+
+pub async fn execute(args: ReprocessArgs) -> Result<()> {
+    let ReprocessArgs {
+        album_id,
+        s3: s3_args,
+        concurrency,
+        thumbnail_size,
+        preview_size,
+        tiers,
+        quality,
+        watermark,
+        quiet,
+    } = args;
+    tracing::info!("Reprocessing album: {}", album_id);
+
+    let defaults = ProcessOptions::default();
+    let process_options = ProcessOptions {
+        thumbnail_size: thumbnail_size.unwrap_or(defaults.thumbnail_size),
+        preview_size: preview_size.unwrap_or(defaults.preview_size),
+        thumbnail_quality: quality.thumbnail_quality,
+        preview_quality: quality.preview_quality,
+        resize_filter: quality.resize_filter,
+        // Originals are never rewritten here
+        watermark: watermark.load(false)?,
+        ..defaults
+    };
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+
+    // Regenerated tiers expire with the rest of the album
+    let expires = manifest.expiry();
+
+    let pb = progress_bar(
+        !quiet && std::io::stderr().is_terminal(),
+        manifest.images.len(),
+        "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}",
+    );
+
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = Vec::new();
+
+    for image in &manifest.images {
+        // Nothing to regenerate from until the original tier is uploaded
+        if !image.has_tier(Tier::Original) {
+            tracing::warn!("Skipping {}: its original hasn't been uploaded", image.original_filename);
+            pb.inc(1);
+            continue;
+        }
+
+        let s3 = s3.clone();
+        let album_id = album_id.clone();
+        let image = image.clone();
+        let permits = Arc::clone(&permits);
+        let pb = pb.clone();
+        let tiers = tiers.clone();
+
+        // Keep AVIF tiers and responsive previews current for images that have them
+        let options = ProcessOptions {
+            avif_speed: image.preview_avif_path.is_some().then_some(AVIF_SPEED),
+            responsive: !image.preview_variants.is_empty(),
+            ..process_options.clone()
+        };
+
+        let filename = image.original_filename.clone();
+        let task = tokio::spawn(async move {
+            let _permit = permits.acquire_owned().await?;
+
+            let original_key = format!("{album_id}/{}", image.original_path);
+            let original = s3.download_file(&original_key).await?;
+            let processed = tokio::task::spawn_blocking(move || process_original(original, &options)).await??;
+
+            if tiers.contains(&Tier::Thumbnail) {
+                let upload_options = tier_upload_options(&s3, &album_id, &image, Tier::Thumbnail, expires).await?;
+                upload(&s3, &album_id, &image.thumbnail_path, processed.thumbnail, &upload_options).await?;
+                if let (Some(path), Some(data)) = (&image.thumbnail_avif_path, processed.thumbnail_avif) {
+                    upload(&s3, &album_id, path, data, &upload_options).await?;
+                }
+            }
+            let mut preview_variants = None;
+            let mut stale_paths = Vec::new();
+            if tiers.contains(&Tier::Preview) {
+                let upload_options = tier_upload_options(&s3, &album_id, &image, Tier::Preview, expires).await?;
+                upload(&s3, &album_id, &image.preview_path, processed.preview, &upload_options).await?;
+                if let (Some(path), Some(data)) = (&image.preview_avif_path, processed.preview_avif) {
+                    upload(&s3, &album_id, path, data, &upload_options).await?;
+                }
+                let mut variants = Vec::new();
+                if !processed.preview_variants.is_empty() {
+                    for variant in processed.preview_variants {
+                        let path = ImageInfo::preview_variant_path(&image.id, variant.size);
+                        upload(&s3, &album_id, &path, variant.data, &upload_options).await?;
+                        variants.push(PreviewVariant { width: variant.width, path, url: None });
+                    }
+                    variants.push(PreviewVariant {
+                        width: processed.preview_width,
+                        path: image.preview_path.clone(),
+                        url: None,
+                    });
+                }
+                stale_paths = image
+                    .preview_variants
+                    .iter()
+                    .map(|variant| variant.path.clone())
+                    .filter(|path| *path != image.preview_path && !variants.iter().any(|variant| &variant.path == path))
+                    .collect();
+                preview_variants = Some(variants);
+            }
+
+            pb.inc(1);
+            pb.set_message(format!("Reprocessed: {}", image.original_filename));
+
+            anyhow::Ok(Regenerated {
+                image_id: image.id,
+                width: processed.width,
+                height: processed.height,
+                lqip: processed.lqip,
+                orientation: processed.orientation,
+                preview_variants,
+                stale_paths,
+            })
+        });
+        tasks.push((filename, task));
+    }
+
+    // Wait for every image; one failure shouldn't throw away the ones that worked
+    let attempted = tasks.len();
+    let mut failed = Vec::new();
+    let mut stale_paths = Vec::new();
+    for (filename, task) in tasks {
+        let outcome = task.await.map_err(anyhow::Error::from).and_then(|result| result);
+        let regenerated = match outcome {
+            Ok(regenerated) => regenerated,
+            Err(e) => {
+                tracing::warn!("Failed to reprocess {}: {:#}", filename, e);
+                failed.push(filename);
+                continue;
+            }
+        };
+        if let Some(image) = manifest.image_mut(&regenerated.image_id) {
+            image.width = regenerated.width;
+            image.height = regenerated.height;
+            image.lqip = Some(regenerated.lqip);
+            image.orientation = regenerated.orientation;
+            if let Some(preview_variants) = regenerated.preview_variants {
+                image.preview_variants = preview_variants;
+            }
+            image.missing_tiers.retain(|tier| !tiers.contains(tier));
+            stale_paths.extend(regenerated.stale_paths);
+        }
+    }
+
+    if failed.is_empty() {
+        pb.finish_with_message("All images reprocessed");
+    } else {
+        pb.finish_with_message(format!("{} image(s) failed", failed.len()));
+    }
+
+    let reprocessed = attempted - failed.len();
+    if attempted > 0 && reprocessed == 0 {
+        anyhow::bail!("All {attempted} images failed to reprocess; the album is unchanged");
+    }
+
+    manifest.update(&s3).await?;
+
+    // Only once the manifest no longer points at them
+    for path in &stale_paths {
+        s3.delete_object(&format!("{album_id}/{path}")).await?;
+    }
+
+    let regenerated: Vec<&str> = [(Tier::Thumbnail, "thumbnails"), (Tier::Preview, "previews")]
+        .into_iter()
+        .filter(|(tier, _)| tiers.contains(tier))
+        .map(|(_, name)| name)
+        .collect();
+    println!("\n✓ Regenerated {} for {reprocessed} images in album {album_id}", regenerated.join(" and "));
+    if !stale_paths.is_empty() {
+        println!("✓ Removed {} preview(s) in sizes no longer generated", stale_paths.len());
+    }
+
+    if !failed.is_empty() {
+        println!("\n⚠ Failed to reprocess {} image(s); their manifest entries are unchanged:", failed.len());
+        for filename in &failed {
+            println!("  - {filename}");
+        }
+        anyhow::bail!("{} image(s) failed to reprocess; run the same command again to retry them", failed.len());
+    }
+
+    Ok(())
+}
+
+/// Storage class, KMS key and expiry to rewrite a tier with: those of its current object, or of
+/// the original for a tier that hasn't been stored yet. Regenerated tiers expire with the album
+/// when they had no expiry of their own.
+async fn tier_upload_options(
+    s3: &S3Client,
+    album_id: &str,
+    image: &ImageInfo,
+    tier: Tier,
+    expires: Option<DateTime>,
+) -> Result<UploadOptions> {
+    let path = match tier {
+        Tier::Thumbnail => &image.thumbnail_path,
+        Tier::Preview => &image.preview_path,
+        Tier::Original => &image.original_path,
+    };
+    let key = format!("{album_id}/{path}");
+    let key = if image.has_tier(tier) && s3.object_exists(&key).await? {
+        key
+    } else {
+        format!("{album_id}/{}", image.original_path)
+    };
+
+    let mut options = s3.upload_options(&key).await?;
+    options.expires = options.expires.or(expires);
+    Ok(options)
+}
+
+async fn upload(s3: &S3Client, album_id: &str, path: &str, data: Vec<u8>, options: &UploadOptions) -> Result<()> {
+    s3.upload_bytes_with(data, &format!("{album_id}/{path}"), options).await
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, image_id: String, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Setting cover of album {} to {}", album_id, image_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let Some(image) = manifest.image_mut(&image_id) else {
+        anyhow::bail!("Image not found in album {album_id}: {image_id}");
+    };
+    let filename = image.original_filename.clone();
+
+    manifest.cover_image_id = Some(image_id.clone());
+    manifest.update(&s3).await?;
+
+    println!("✓ Cover set to {filename} ({image_id})");
+
+    Ok(())
+}
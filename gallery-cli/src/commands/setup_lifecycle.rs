@@ -0,0 +1,15 @@
+use anyhow::Result;
+use super::S3Args;
+
+pub async fn execute(days: u32, s3_args: S3Args) -> Result<()> {
+    let days = i32::try_from(days)?;
+    tracing::info!("Installing {}-day expiration lifecycle rule", days);
+
+    let s3 = s3_args.connect().await?;
+    s3.put_expiration_lifecycle(days).await?;
+
+    println!("✓ Album objects will expire {days} days after upload");
+    println!("  Note: this replaced any lifecycle rules previously set on the bucket");
+
+    Ok(())
+}
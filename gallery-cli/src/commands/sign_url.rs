@@ -0,0 +1,22 @@
+use anyhow::Result;
+use gallery_core::{signing, AlbumManifest};
+
+/// Print a gallery link that the web app accepts until `expires_secs` from now.
+/// The web app must be running with the same secret in `GALLERY_SIGNING_KEY`.
+pub fn execute(album_id: String, secret_env: String, expires_secs: u64) -> Result<()> {
+    if !AlbumManifest::is_valid_id(&album_id) {
+        anyhow::bail!("Invalid album ID: {album_id:?}");
+    }
+
+    let secret = match std::env::var(&secret_env) {
+        Ok(secret) if !secret.is_empty() => secret,
+        _ => anyhow::bail!("No signing key: set {secret_env} to the secret the web app uses"),
+    };
+
+    let expires_at = chrono::Utc::now().timestamp() + i64::try_from(expires_secs)?;
+    let token = signing::sign_gallery_link(secret.as_bytes(), &album_id, expires_at);
+
+    println!("/gallery/{album_id}?token={token}&exp={expires_at}");
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use gallery_core::{AlbumManifest, Tier};
+use gallery_templates::GalleryTemplate;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use super::{progress_bar, S3Args};
+
+pub async fn execute(album_id: String, dest_dir: PathBuf, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Exporting album {} as a static site to {}", album_id, dest_dir.display());
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    if manifest.password_hash.is_some() {
+        println!("⚠ Album {album_id} is password-protected; the static export is not, so host it privately");
+        manifest.password_hash = None;
+    }
+    if manifest.is_expired() {
+        println!("⚠ Album {album_id} has expired; the export is kept forever unless you remove it");
+    }
+
+    fs::create_dir_all(&dest_dir)
+        .with_context(|| format!("Failed to create {}", dest_dir.display()))?;
+
+    // AVIF renditions are only picked by the web server's content negotiation; the page
+    // itself asks for the JPEGs, so those are all an export needs
+    let paths: Vec<String> = manifest
+        .images
+        .iter()
+        .flat_map(|image| image.object_paths())
+        .filter(|path| !path.ends_with(".avif"))
+        .map(str::to_string)
+        .collect();
+
+    let pb = progress_bar(true, paths.len(), "[{elapsed_precise}] {bar:40.green/blue} {pos}/{len} {msg}");
+
+    let mut total_bytes = 0;
+    for path in &paths {
+        // Never let a manifest entry escape the destination directory
+        let relative = Path::new(path);
+        if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+            anyhow::bail!("Refusing to export unsafe object path {path:?}");
+        }
+        let local_path = dest_dir.join(relative);
+        if let Some(parent) = local_path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        pb.set_message(format!("Downloading: {path}"));
+        let data = s3.download_file(&format!("{album_id}/{path}")).await?;
+        total_bytes += data.len() as u64;
+
+        // Write under a temporary name so an interrupted export never leaves a truncated file behind
+        let partial_path = local_path.with_extension("part");
+        fs::write(&partial_path, data).with_context(|| format!("Failed to write {}", partial_path.display()))?;
+        fs::rename(&partial_path, &local_path).with_context(|| format!("Failed to write {}", local_path.display()))?;
+
+        pb.inc(1);
+    }
+
+    pb.finish_with_message("Download complete");
+
+    link_exported_files(&mut manifest);
+    let page = GalleryTemplate::render_static(&album_id, &manifest).context("Failed to render the gallery page")?;
+    let index_path = dest_dir.join("index.html");
+    fs::write(&index_path, page).with_context(|| format!("Failed to write {}", index_path.display()))?;
+
+    println!("\n✓ Exported album {album_id} as a static site");
+    println!("  {} images, {} files ({:.1} MB)", manifest.images.len(), paths.len(), total_bytes as f64 / (1024.0 * 1024.0));
+    println!("Destination: {}", dest_dir.display());
+    println!("Open {} or upload the directory to any static host", index_path.display());
+
+    Ok(())
+}
+
+/// Point every image's URLs at the exported files, relative to `index.html`, with the same
+/// fallbacks the web server uses for images missing a tier
+fn link_exported_files(manifest: &mut AlbumManifest) {
+    for image in &mut manifest.images {
+        if !image.has_tier(Tier::Preview) && image.has_tier(Tier::Original) {
+            image.preview_path = image.original_path.clone();
+        }
+        if !image.has_tier(Tier::Thumbnail) {
+            image.thumbnail_path = image.preview_path.clone();
+        }
+
+        image.thumbnail_url = Some(image.thumbnail_path.clone());
+        image.preview_url = Some(image.preview_path.clone());
+        for variant in &mut image.preview_variants {
+            variant.url = Some(variant.path.clone());
+        }
+        image.original_url = image.has_tier(Tier::Original).then(|| image.original_path.clone());
+    }
+}
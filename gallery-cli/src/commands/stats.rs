@@ -0,0 +1,84 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use super::upload::OutputFormat;
+use super::{format_bytes, S3Args};
+
+/// Object count and total size of one kind of object
+#[derive(Default, Serialize)]
+struct Usage {
+    objects: u64,
+    bytes: u64,
+}
+
+impl Usage {
+    fn add(&mut self, bytes: u64) {
+        self.objects += 1;
+        self.bytes += bytes;
+    }
+}
+
+/// Summary printed with `--output json`
+#[derive(Default, Serialize)]
+struct BucketStats {
+    albums: usize,
+    total: Usage,
+    originals: Usage,
+    previews: Usage,
+    thumbnails: Usage,
+    manifests: Usage,
+    /// Objects outside the album layout
+    other: Usage,
+}
+
+pub async fn execute(output: OutputFormat, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Collecting bucket storage stats");
+
+    let s3 = s3_args.connect().await?;
+
+    // One listing of the whole bucket; its sizes avoid a HEAD per object
+    let objects = s3.list_objects("").await?;
+
+    let mut stats = BucketStats::default();
+    let mut albums = HashSet::new();
+    for (key, size) in &objects {
+        stats.total.add(*size);
+        let (album_id, rest) = key.split_once('/').unwrap_or(("", key));
+        let usage = if rest == "manifest.json" {
+            albums.insert(album_id);
+            &mut stats.manifests
+        } else if rest.starts_with("originals/") {
+            &mut stats.originals
+        } else if rest.starts_with("previews/") {
+            &mut stats.previews
+        } else if rest.starts_with("thumbnails/") {
+            &mut stats.thumbnails
+        } else {
+            &mut stats.other
+        };
+        usage.add(*size);
+    }
+    stats.albums = albums.len();
+
+    if output == OutputFormat::Json {
+        println!("{}", serde_json::to_string(&stats)?);
+        return Ok(());
+    }
+
+    println!("Albums: {}", stats.albums);
+    println!("Objects: {} ({})", stats.total.objects, format_bytes(stats.total.bytes));
+    for (label, usage) in [
+        ("Originals", &stats.originals),
+        ("Previews", &stats.previews),
+        ("Thumbnails", &stats.thumbnails),
+        ("Manifests", &stats.manifests),
+        ("Other", &stats.other),
+    ] {
+        if usage.objects > 0 {
+            println!("  {label:<11} {:>8} objects  {:>10}", usage.objects, format_bytes(usage.bytes));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, image_id: String, tags: Vec<String>, s3_args: S3Args) -> Result<()> {
+    // Tags are matched case-insensitively and end up in a space-separated HTML attribute
+    let mut tags: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    if let Some(tag) = tags.iter().find(|tag| tag.contains(char::is_whitespace)) {
+        anyhow::bail!("Tags cannot contain spaces: {tag:?}");
+    }
+    tags.sort();
+    tags.dedup();
+
+    tracing::info!("Setting tags for image {} in album {}", image_id, album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let mut manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+    let Some(image) = manifest.image_mut(&image_id) else {
+        anyhow::bail!("Image not found in album {album_id}: {image_id}");
+    };
+    image.tags = tags;
+    let tags = image.tags.join(", ");
+    manifest.update(&s3).await?;
+
+    if tags.is_empty() {
+        println!("✓ Tags cleared for {image_id}");
+    } else {
+        println!("✓ Tags set for {image_id}: {tags}");
+    }
+
+    Ok(())
+}
@@ -1,62 +1,541 @@
-use anyhow::Result;
-use gallery_core::{AlbumManifest, ImageInfo, S3Client, DateTime};
-use indicatif::{ProgressBar, ProgressStyle};
+use anyhow::{Context, Result};
+use gallery_core::{AlbumManifest, DateTime, ImageInfo, PreviewVariant, S3Client, StorageClass, Theme, Tier, UploadOptions};
 use rayon::prelude::*;
+use serde::Serialize;
 use sha2::{Sha256, Digest};
 use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs;
+use std::io::{BufReader, IsTerminal, Read};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 use uuid::Uuid;
 use walkdir::WalkDir;
 use chrono::{Duration, Utc};
+use clap::{Args, ValueEnum};
 
-use crate::image_processor::{is_image_file, process_image, ProcessedImage};
+use super::{progress_bar, QualityArgs, S3Args, WatermarkArgs};
+use crate::config::Config;
+use crate::image_processor::{is_heic_file, is_image_file, process_image, ProcessOptions, ProcessedImage};
+
+/// Images uploaded at once unless --concurrency or the config file says otherwise
+const DEFAULT_CONCURRENCY: usize = 16;
+
+/// Display order of images in the uploaded album
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OrderBy {
+    /// Keep the sorted source path order
+    Path,
+    /// Sort by EXIF capture date, falling back to filename
+    CaptureDate,
+}
+
+/// Arrangement of thumbnails on the gallery page
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum GalleryLayout {
+    /// Rows of equal height keeping each photo's aspect ratio
+    Justified,
+    /// Uniform square cells with photos cropped to fill them
+    Square,
+}
+
+impl GalleryLayout {
+    /// Value stored in the manifest's `layout` field
+    fn as_str(self) -> &'static str {
+        match self {
+            GalleryLayout::Justified => "justified",
+            GalleryLayout::Square => "square",
+        }
+    }
+}
+
+/// Encodings generated for previews and thumbnails
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TierFormat {
+    /// JPEG only
+    Jpeg,
+    /// AVIF in addition to the JPEG fallback, for browsers that accept it
+    Avif,
+}
+
+/// What a command prints to stdout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable progress and summary
+    Text,
+    /// A single JSON object describing the result
+    Json,
+}
+
+/// Summary printed with `--output json`
+#[derive(Serialize)]
+struct UploadReport {
+    album_id: String,
+    content_hash: String,
+    name: String,
+    total_images: usize,
+    uploaded: usize,
+    reused: usize,
+    /// Images pruned from the album by `--replace`
+    removed: usize,
+    /// Files that couldn't be read or decoded
+    skipped: Vec<String>,
+    /// Files whose upload to S3 failed; left out of the manifest
+    failed: Vec<String>,
+    gallery_path: String,
+}
+
+/// Print decorative output unless JSON output was requested
+macro_rules! say {
+    ($json:expr) => {
+        if !$json {
+            println!();
+        }
+    };
+    ($json:expr, $($arg:tt)*) => {
+        if !$json {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Args)]
+pub struct UploadArgs {
+    /// Directory or files to upload
+    #[arg(required = true)]
+    pub paths: Vec<String>,
+
+    /// Album name (default: the existing album's name, else the photos' capture months or their folder name)
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Human-readable album ID used in URLs, e.g. `my-wedding-2024` (default: content hash)
+    #[arg(long, value_parser = parse_slug)]
+    pub slug: Option<String>,
+
+    #[command(flatten)]
+    pub s3: S3Args,
+
+    /// Days until gallery objects expire (default: keep forever).
+    /// Presigned URLs are capped at 7 days but are re-issued on every page view,
+    /// so links keep working for as long as the objects exist.
+    #[arg(long)]
+    pub expires_in_days: Option<u32>,
+
+    /// Show what would be uploaded without writing anything to S3
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Display order of images in the gallery
+    #[arg(long, value_enum, default_value_t = OrderBy::Path)]
+    pub order_by: OrderBy,
+
+    /// Album description shown under the title (re-uploads keep the current one unless given)
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Date of the event or shoot (YYYY-MM-DD)
+    #[arg(long, value_parser = parse_event_date)]
+    pub event_date: Option<String>,
+
+    /// Require this password to view the gallery (only a hash is stored)
+    #[arg(long)]
+    pub password: Option<String>,
+
+    /// S3 storage class for image objects, e.g. STANDARD_IA or GLACIER_IR (default: STANDARD).
+    /// GLACIER and DEEP_ARCHIVE objects can't be served until restored.
+    #[arg(long, value_parser = parse_storage_class)]
+    pub storage_class: Option<StorageClass>,
+
+    /// Encrypt uploaded objects with SSE-KMS using this key ID or ARN
+    #[arg(long)]
+    pub sse_kms_key_id: Option<String>,
+
+    /// Output format; `json` prints only a result object to stdout (progress bars stay on stderr)
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, conflicts_with = "dry_run")]
+    pub output: OutputFormat,
+
+    /// Maximum number of images uploaded to S3 at once [default: 16]
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub concurrency: Option<usize>,
+
+    /// Limit the whole upload to N jobs: N threads hash and process images, and N images upload
+    /// at once unless --concurrency says otherwise [default: all CPU cores, 16 uploads]
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub jobs: Option<usize>,
+
+    /// Longest edge of grid thumbnails in pixels [default: 400]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..))]
+    pub thumbnail_size: Option<u32>,
+
+    /// Longest edge of lightbox previews in pixels [default: 2048]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..))]
+    pub preview_size: Option<u32>,
+
+    #[command(flatten)]
+    pub quality: QualityArgs,
+
+    /// Upload only the first N images (in sorted path order), e.g. to try out a new bucket.
+    /// The content-derived album ID then covers just those N images.
+    #[arg(long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    pub limit: Option<usize>,
+
+    /// Refuse to upload more than this many images (checked before anything is read)
+    #[arg(long)]
+    pub max_images: Option<usize>,
+
+    /// Refuse to upload more than this many megabytes of originals in total
+    #[arg(long)]
+    pub max_total_mb: Option<u64>,
+
+    /// Upload even if --max-images or --max-total-mb is exceeded
+    #[arg(long)]
+    pub force: bool,
+
+    /// How deep to search given directories; 1 means only files directly inside (default: unlimited)
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Follow symbolic links while walking directories
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Abort on the first image that can't be read or decoded instead of skipping it
+    #[arg(long)]
+    pub fail_fast: bool,
+
+    /// Preview/thumbnail encoding; `avif` adds AVIF tiers served to browsers that accept them
+    #[arg(long, value_enum, default_value_t = TierFormat::Jpeg)]
+    pub format: TierFormat,
+
+    /// AVIF encoder speed from 1 (slowest, smallest files) to 10 (fastest)
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u8).range(1..=10))]
+    pub avif_speed: u8,
+
+    /// Encode previews as baseline JPEGs instead of progressive (slightly faster to encode)
+    #[arg(long)]
+    pub baseline_previews: bool,
+
+    /// Also store 640px and 1280px previews so phones and small screens load a fitting size
+    #[arg(long)]
+    pub responsive: bool,
+
+    /// Make the album exactly the given images: drop images that aren't in the source set
+    /// from the manifest and delete their objects (by default they're kept). Needs --slug, since
+    /// a changed image set otherwise gets a new content-hash album ID. Nothing is removed when
+    /// any file fails to process or upload
+    #[arg(long, requires = "slug")]
+    pub replace: bool,
+
+    /// Reprocess and re-upload images already in the album, replacing their objects in place
+    /// (same image IDs, captions and tags), e.g. after changing sizes, quality or format
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Seconds each image is shown when viewers play the album as a slideshow (default: 5;
+    /// 0 goes back to the default)
+    #[arg(long)]
+    pub slideshow_interval: Option<u32>,
+
+    /// Restart the slideshow from the first image instead of stopping at the last
+    #[arg(long, conflicts_with = "no_slideshow_loop")]
+    pub slideshow_loop: bool,
+
+    /// Stop the slideshow at the last image again on an album uploaded with `--slideshow-loop`
+    #[arg(long)]
+    pub no_slideshow_loop: bool,
+
+    /// Thumbnail grid of the gallery page (default: justified); kept on later uploads unless given again
+    #[arg(long, value_enum)]
+    pub layout: Option<GalleryLayout>,
+
+    /// Hex color of focus outlines and selected tag chips, e.g. "#c0392b" [default: #333]
+    #[arg(long, value_parser = parse_hex_color)]
+    pub accent_color: Option<String>,
+
+    /// Hex color of the gallery page background [default: #ffffff]
+    #[arg(long, value_parser = parse_hex_color)]
+    pub background_color: Option<String>,
+
+    /// CSS font-family list for the gallery page, e.g. "Georgia, serif" [default: the system font]
+    #[arg(long, value_parser = parse_font)]
+    pub font: Option<String>,
+
+    /// Make the gallery view-only: no Download button, and `?download` requests are refused.
+    /// Kept on later uploads unless `--allow-download` is given
+    #[arg(long, conflicts_with = "allow_download")]
+    pub no_download: bool,
+
+    /// Bring back the Download button on an album uploaded with `--no-download`
+    #[arg(long)]
+    pub allow_download: bool,
+
+    #[command(flatten)]
+    pub watermark: WatermarkArgs,
+
+    /// Also burn the watermark into stored originals (re-encoded as JPEG, dropping their EXIF)
+    #[arg(long, requires = "watermark")]
+    pub watermark_original: bool,
+
+    /// Tiers to upload, comma-separated from thumbnail, preview and original. Leaving some out
+    /// makes a quick shareable album; run again with the missing tiers to fill them in later.
+    #[arg(long, value_delimiter = ',', default_value = "thumbnail,preview,original")]
+    pub tiers: Vec<Tier>,
+
+    /// Also write the uploaded manifest to this local file, e.g. as a backup
+    #[arg(long, conflicts_with = "dry_run")]
+    pub save_manifest: Option<PathBuf>,
+
+    /// Hide progress bars (they are also hidden automatically when not attached to a terminal)
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+impl UploadArgs {
+    /// Fill in defaults from the config file for anything not given on the command line
+    pub fn with_config(mut self, config: &Config) -> Self {
+        self.s3 = self.s3.with_config(config);
+        self.concurrency = self.concurrency.or(self.jobs).or(config.concurrency);
+        self.thumbnail_size = self.thumbnail_size.or(config.thumbnail_size);
+        self.preview_size = self.preview_size.or(config.preview_size);
+        self
+    }
+}
+
+fn parse_event_date(value: &str) -> Result<String, String> {
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.to_string())
+        .map_err(|_| format!("expected a date as YYYY-MM-DD, got {value:?}"))
+}
+
+fn parse_hex_color(value: &str) -> Result<String, String> {
+    if Theme::is_valid_color(value) {
+        Ok(value.to_ascii_lowercase())
+    } else {
+        Err(format!("expected a hex color like #333 or #1a2b3c, got {value:?}"))
+    }
+}
+
+fn parse_font(value: &str) -> Result<String, String> {
+    if Theme::is_valid_font(value) {
+        Ok(value.trim().to_string())
+    } else {
+        Err(format!("expected a font-family list of names, quotes and commas, got {value:?}"))
+    }
+}
+
+fn parse_slug(value: &str) -> Result<String, String> {
+    if AlbumManifest::is_valid_id(value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!(
+            "invalid slug {value:?}; use 1-64 lowercase letters, digits and hyphens (not at the start or end)"
+        ))
+    }
+}
+
+fn parse_storage_class(value: &str) -> Result<StorageClass, String> {
+    let value = value.to_uppercase();
+    if !StorageClass::values().contains(&value.as_str()) {
+        return Err(format!(
+            "unknown storage class {value:?}; expected one of: {} \
+             (note: GLACIER and DEEP_ARCHIVE make presigned GETs fail until objects are restored)",
+            StorageClass::values().join(", ")
+        ));
+    }
+    Ok(StorageClass::from(value.as_str()))
+}
 
 /// Convert chrono DateTime to AWS SDK DateTime
 fn to_aws_datetime(dt: chrono::DateTime<Utc>) -> DateTime {
     DateTime::from_secs(dt.timestamp())
 }
 
-pub async fn execute(paths: Vec<String>, name: String, bucket: String, expires_in_days: u32) -> Result<()> {
-    // Initialize S3 client
-    let s3 = S3Client::new(bucket).await?;
+/// When the album expires: `--expires-in-days` from now, otherwise whenever the existing album
+/// already does, so a re-upload without the flag doesn't make an expiring album permanent
+fn album_expires_at(expires_in_days: Option<u32>, existing: Option<&AlbumManifest>) -> Option<chrono::DateTime<Utc>> {
+    match expires_in_days {
+        Some(days) => Some(Utc::now() + Duration::days(i64::from(days))),
+        None => existing
+            .and_then(|manifest| manifest.expires_at.as_deref())
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.with_timezone(&Utc)),
+    }
+}
+
+pub async fn execute(args: UploadArgs) -> Result<()> {
+    let UploadArgs {
+        paths,
+        name,
+        slug,
+        s3: s3_args,
+        expires_in_days,
+        dry_run,
+        order_by,
+        description,
+        event_date,
+        password,
+        storage_class,
+        sse_kms_key_id,
+        output,
+        concurrency,
+        jobs,
+        thumbnail_size,
+        preview_size,
+        quality,
+        limit,
+        max_images,
+        max_total_mb,
+        force,
+        max_depth,
+        follow_symlinks,
+        fail_fast,
+        format,
+        avif_speed,
+        baseline_previews,
+        responsive,
+        replace,
+        overwrite,
+        slideshow_interval,
+        slideshow_loop,
+        no_slideshow_loop,
+        layout,
+        accent_color,
+        background_color,
+        font,
+        no_download,
+        allow_download,
+        watermark,
+        watermark_original,
+        tiers,
+        save_manifest,
+        quiet,
+    } = args;
+    let json_output = output == OutputFormat::Json;
+    // Bars draw on stderr; skip them when asked or when nobody is watching (CI logs, redirects)
+    let show_progress = !quiet && std::io::stderr().is_terminal();
+
+    let defaults = ProcessOptions::default();
+    let process_options = ProcessOptions {
+        thumbnail_size: thumbnail_size.unwrap_or(defaults.thumbnail_size),
+        preview_size: preview_size.unwrap_or(defaults.preview_size),
+        thumbnail_quality: quality.thumbnail_quality,
+        preview_quality: quality.preview_quality,
+        resize_filter: quality.resize_filter,
+        avif_speed: (format == TierFormat::Avif).then_some(avif_speed),
+        progressive_previews: !baseline_previews,
+        watermark: watermark.load(watermark_original)?,
+        responsive,
+    };
+    let concurrency = concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+    // With --jobs, hashing and processing (including encoders' own parallelism) run on a pool
+    // of that size instead of rayon's global one with a thread per core
+    let pool = jobs
+        .map(|jobs| rayon::ThreadPoolBuilder::new().num_threads(jobs).build())
+        .transpose()
+        .context("Failed to start the processing thread pool")?;
 
-    // Calculate expiration times
-    // Images expire at N days + 1 hour
-    let image_expires = to_aws_datetime(Utc::now() + Duration::days(expires_in_days as i64) + Duration::hours(1));
-    // Manifest expires at exactly N days
-    let manifest_expires = to_aws_datetime(Utc::now() + Duration::days(expires_in_days as i64));
+    if matches!(storage_class, Some(StorageClass::Glacier | StorageClass::DeepArchive)) {
+        say!(json_output, "⚠ Images in GLACIER/DEEP_ARCHIVE can't be viewed in the gallery until restored\n");
+    }
+
+    // Hash up front so a bad password fails before any processing
+    let password_hash = password
+        .filter(|p| !p.is_empty())
+        .map(|p| gallery_core::password::hash_password(&p))
+        .transpose()?;
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
 
     // Collect all image paths
-    let image_paths = collect_image_paths(paths)?;
+    let walk = WalkOptions {
+        max_depth,
+        follow_symlinks,
+    };
+    let mut image_paths = collect_image_paths(paths, walk)?;
 
     if image_paths.is_empty() {
         anyhow::bail!("No images found in the provided paths");
     }
 
-    // Create deterministic album ID from the sorted list of image paths
-    // This ensures the same set of images always produces the same album ID
-    let album_id = compute_album_id(&image_paths);
+    if let Some(limit) = limit.filter(|&limit| limit < image_paths.len()) {
+        say!(json_output, "Limiting upload to the first {limit} of {} images\n", image_paths.len());
+        image_paths.truncate(limit);
+    }
 
-    println!("Album: {name}");
-    println!("Album ID: {album_id}");
-    println!("Image set size: {}\n", image_paths.len());
+    if !force {
+        check_upload_size(&image_paths, max_images, max_total_mb)?;
+    }
+
+    // Hash every file up front; the hashes identify both the album and already-uploaded images
+    let mut skipped = Vec::new();
+    let hash_pb = progress_bar(show_progress, image_paths.len(), "[{elapsed_precise}] {bar:40.yellow/blue} {pos}/{len} {msg}");
+    hash_pb.set_message("Hashing images...");
+
+    let hash_outcomes: Vec<_> = run_in(pool.as_ref(), || {
+        image_paths
+            .par_iter()
+            .map(|path| {
+                let outcome = hash_file(path).map(|hash| (path.clone(), hash));
+                hash_pb.inc(1);
+                (path, outcome)
+            })
+            .collect()
+    });
+
+    hash_pb.finish_with_message("Hashing complete");
+    say!(json_output);
+
+    let hashed_images = keep_successes(hash_outcomes, fail_fast, &mut skipped)?;
+
+    if hashed_images.is_empty() {
+        anyhow::bail!("None of the {} images could be read", image_paths.len());
+    }
+
+    // Create deterministic album ID from the set of file contents
+    // This ensures the same images produce the same album ID wherever they live on disk
+    let content_hash = compute_album_id(hashed_images.iter().map(|(_, hash)| hash.as_str()));
+    // A slug replaces the hash in URLs; the hash is still recorded in the manifest
+    let album_id = slug.unwrap_or_else(|| content_hash.clone());
+
+    if let Some(name) = &name {
+        say!(json_output, "Album: {name}");
+    }
+    say!(json_output, "Album ID: {album_id}");
+    say!(json_output, "Image set size: {}\n", hashed_images.len());
 
     // Check if this album already exists
-    let manifest_key = format!("{album_id}/manifest.json");
-    let existing_manifest = if s3.object_exists(&manifest_key).await? {
-        println!("✓ Found existing album with this image set");
-        println!("  Checking which images need to be uploaded...\n");
-
-        let manifest_data = s3.download_file(&manifest_key).await?;
-        let manifest_json = String::from_utf8(manifest_data)?;
-        Some(AlbumManifest::from_json(&manifest_json)?)
+    let existing_manifest = if s3.object_exists(&AlbumManifest::key(&album_id)).await? {
+        say!(json_output, "✓ Found existing album with this image set");
+        say!(json_output, "  Checking which images need to be uploaded...\n");
+
+        Some(AlbumManifest::fetch(&s3, &album_id).await?)
     } else {
-        println!("✓ New album - will upload all images\n");
+        say!(json_output, "✓ New album - will upload all images\n");
         None
     };
 
+    // Calculate expiration times (none when keeping the album forever)
+    // Manifest expires at exactly N days, or when the existing album already does
+    let manifest_expires_at = album_expires_at(expires_in_days, existing_manifest.as_ref());
+    let manifest_expires = manifest_expires_at.map(to_aws_datetime);
+    // Images expire an hour after the manifest
+    let image_expires = manifest_expires_at.map(|at| to_aws_datetime(at + Duration::hours(1)));
+
+    let image_options = UploadOptions {
+        expires: image_expires,
+        storage_class,
+        sse_kms_key_id: sse_kms_key_id.clone(),
+    };
+    // The manifest keeps the default storage class so the web app can always read it
+    let manifest_options = UploadOptions {
+        expires: manifest_expires,
+        storage_class: None,
+        sse_kms_key_id,
+    };
+
     // Build hash map of existing images if resume mode
     let existing_images: HashMap<String, ImageInfo> = existing_manifest
         .as_ref()
@@ -68,188 +547,560 @@ pub async fn execute(paths: Vec<String>, name: String, bucket: String, expires_i
         })
         .unwrap_or_default();
 
-    // Process images in parallel using rayon (CPU-bound work)
-    // For each image: hash file, check if exists, process if needed
-    let process_pb = ProgressBar::new(image_paths.len() as u64);
-    process_pb.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
-            .expect("Invalid progress bar template")
-            .progress_chars("█▓▒░ "),
-    );
-    process_pb.set_message("Processing images...");
-
-    let pb = Arc::new(process_pb);
-    let existing_images = Arc::new(existing_images);
-
-    enum ProcessResult {
-        Existing(ImageInfo),
-        New(String, String, String, ProcessedImage), // image_id, filename, hash, processed
+    if dry_run {
+        return print_dry_run_plan(&album_id, &hashed_images, &existing_images, &tiers, overwrite, watermark_original);
     }
 
-    let process_results: Vec<_> = image_paths
-        .par_iter()
-        .map(|path| {
-            let filename = path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+    // Images already in the album are reused as-is; only new ones and those still missing
+    // a requested tier need processing (all of them with --overwrite)
+    let (reused_images, to_process): (Vec<_>, Vec<_>) = hashed_images
+        .iter()
+        .map(|(path, file_hash)| (path, file_hash, existing_images.get(file_hash).cloned()))
+        .partition(|(_, _, existing)| {
+            !overwrite && existing.as_ref().is_some_and(|img| tiers.iter().all(|&tier| img.has_tier(tier)))
+        });
+    let reused_images: Vec<ImageInfo> = reused_images
+        .into_iter()
+        .filter_map(|(_, _, existing)| existing)
+        .collect();
 
-            // Show we're starting this file
-            pb.set_message(format!("Hashing: {filename}"));
-
-            // Hash the file content
-            let file_hash = hash_file(path)?;
+    say!(
+        json_output,
+        "Images: {} total ({} already uploaded, {} to process)\n",
+        hashed_images.len(),
+        reused_images.len(),
+        to_process.len()
+    );
 
-            // Check if this image already exists in the album
-            if let Some(existing_info) = existing_images.get(&file_hash) {
-                pb.inc(1);
-                pb.set_message(format!("Skipped (exists): {filename}"));
-                return Ok::<_, anyhow::Error>(ProcessResult::Existing(existing_info.clone()));
-            }
+    // Process new images in parallel using rayon (CPU-bound work)
+    let pb = progress_bar(show_progress, to_process.len(), "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}");
+    pb.set_message("Processing images...");
 
-            // Process the image (new or changed)
-            pb.set_message(format!("Processing: {filename}"));
-            let image_id = Uuid::new_v4().to_string();
-            let processed = process_image(path)?;
+    let outcomes: Vec<_> = run_in(pool.as_ref(), || {
+        to_process
+            .par_iter()
+            .map(|(path, file_hash, existing)| {
+                let filename = utf8_name(path.file_name().unwrap_or_default());
 
-            pb.inc(1);
-            pb.set_message(format!("Processed: {filename}"));
+                pb.set_message(format!("Processing: {filename}"));
+                let outcome = process_image(path, &process_options)
+                    .map(|processed| (filename.clone(), (*file_hash).clone(), existing.clone(), processed));
 
-            Ok(ProcessResult::New(image_id, filename, file_hash, processed))
-        })
-        .collect::<Result<Vec<_>>>()?;
+                pb.inc(1);
+                pb.set_message(format!("Processed: {filename}"));
+                (*path, outcome)
+            })
+            .collect()
+    });
 
     pb.finish_with_message("Processing complete");
-    println!();
+    say!(json_output);
 
-    // Separate existing images from new ones
-    let mut reused_images = Vec::new();
-    let mut new_images = Vec::new();
+    let new_images = keep_successes(outcomes, fail_fast, &mut skipped)?;
 
-    for result in process_results {
-        match result {
-            ProcessResult::Existing(image_info) => reused_images.push(image_info),
-            ProcessResult::New(image_id, filename, file_hash, processed) => {
-                new_images.push((image_id, filename, file_hash, processed));
-            }
-        }
+    if reused_images.is_empty() && new_images.is_empty() {
+        anyhow::bail!("None of the {} images could be processed", image_paths.len());
     }
 
-    println!(
-        "Images: {} total ({} already uploaded, {} to upload)\n",
-        image_paths.len(),
-        reused_images.len(),
-        new_images.len()
-    );
+    let reused_count = reused_images.len();
 
     // Upload new images concurrently using tokio (I/O-bound work)
     let mut uploaded_images = Vec::new();
+    let mut failed_uploads = Vec::new();
+    // Objects replaced by --overwrite under a different key, deleted once the manifest stops listing them
+    let mut stale_paths = Vec::new();
 
     if !new_images.is_empty() {
-        let upload_pb = ProgressBar::new(new_images.len() as u64);
-        upload_pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] {bar:40.green/blue} {pos}/{len} {msg}")
-                .expect("Invalid progress bar template")
-                .progress_chars("█▓▒░ "),
-        );
+        let upload_pb = progress_bar(show_progress, new_images.len(), "[{elapsed_precise}] {bar:40.green/blue} {pos}/{len} {msg}");
         upload_pb.set_message("Uploading to S3...");
 
         let mut upload_tasks = Vec::new();
+        let upload_permits = Arc::new(Semaphore::new(concurrency));
 
-        for (image_id, filename, file_hash, processed) in new_images {
+        for (filename, file_hash, existing, processed) in new_images {
             let s3_clone = s3.clone();
             let album_id_clone = album_id.clone();
             let pb_clone = upload_pb.clone();
-            let image_expires_clone = image_expires;
+            let image_options_clone = image_options.clone();
+            let permits = Arc::clone(&upload_permits);
+            let tiers = tiers.clone();
 
-            // Spawn concurrent upload task
+            // Spawn concurrent upload task, at most `concurrency` running at once
+            let task_filename = filename.clone();
             let task = tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await?;
                 let result =
-                    upload_image_to_s3(s3_clone, album_id_clone, image_id, filename.clone(), file_hash, processed, image_expires_clone)
+                    upload_image_to_s3(s3_clone, album_id_clone, task_filename.clone(), file_hash, existing, processed, image_options_clone, tiers)
                         .await;
                 pb_clone.inc(1);
-                pb_clone.set_message(format!("Uploaded: {filename}"));
+                pb_clone.set_message(format!("Uploaded: {task_filename}"));
                 result
             });
 
-            upload_tasks.push(task);
+            upload_tasks.push((filename, task));
         }
 
-        // Wait for all uploads to complete and collect results
-        for task in upload_tasks {
-            let image_info = task.await??;
-            uploaded_images.push(image_info);
+        // Wait for every upload; one failure shouldn't throw away the ones that worked
+        for (filename, task) in upload_tasks {
+            let outcome = task.await.map_err(anyhow::Error::from).and_then(|result| result);
+            match outcome {
+                Ok((image_info, stale)) => {
+                    uploaded_images.push(image_info);
+                    stale_paths.extend(stale);
+                }
+                Err(e) if fail_fast => return Err(e.context(format!("Failed to upload {filename}"))),
+                Err(e) => {
+                    tracing::warn!("Failed to upload {}: {:#}", filename, e);
+                    failed_uploads.push(filename);
+                }
+            }
         }
 
-        upload_pb.finish_with_message("All new images uploaded");
-        println!();
+        if failed_uploads.is_empty() {
+            upload_pb.finish_with_message("All new images uploaded");
+        } else {
+            upload_pb.finish_with_message(format!("{} upload(s) failed", failed_uploads.len()));
+        }
+        say!(json_output);
+    }
+
+    if reused_images.is_empty() && uploaded_images.is_empty() {
+        anyhow::bail!("All {} image uploads failed; nothing to write to the album", failed_uploads.len());
     }
+    let uploaded_count = uploaded_images.len();
+
+    // Without --name, keep the album's current name or derive one from the photos
+    let name = match (name, &existing_manifest) {
+        (Some(name), _) => name,
+        (None, Some(existing)) => existing.name.clone(),
+        (None, None) => {
+            let name = default_album_name(reused_images.iter().chain(&uploaded_images), &image_paths);
+            say!(json_output, "Album name: {name} (pass --name to choose another)\n");
+            name
+        }
+    };
 
     // Create new manifest with all images (reused + newly uploaded)
     let mut manifest = AlbumManifest::with_id(name, album_id.clone());
+    manifest.content_hash = Some(content_hash.clone());
+    manifest.expires_at = manifest_expires_at.map(|at| at.to_rfc3339());
+    manifest.description = description.filter(|d| !d.trim().is_empty());
+    manifest.event_date = event_date;
+    manifest.slideshow_interval_secs = slideshow_interval.filter(|&secs| secs > 0);
+    manifest.slideshow_loop = slideshow_loop.then_some(true);
+    manifest.layout = layout.map(|layout| layout.as_str().to_string());
+    let theme = Theme { accent_color, background_color, font };
+    manifest.theme = (theme.accent_color.is_some() || theme.background_color.is_some() || theme.font.is_some())
+        .then_some(theme);
+    manifest.allow_download = !no_download;
 
     // Add all images to manifest
-    for image in reused_images.into_iter().chain(uploaded_images.into_iter()) {
+    for image in reused_images.into_iter().chain(uploaded_images) {
         manifest.add_image(image);
     }
 
+    // Images in the existing album that aren't part of this upload
+    let mut dropped_images = Vec::new();
+    // A file that failed may be one the album already has (always, with --overwrite), so only
+    // prune after a clean run; otherwise those images would be deleted instead of kept
+    let prune = replace && skipped.is_empty() && failed_uploads.is_empty();
+
+    // Carry album-level settings over from a previous upload of this image set
+    if let Some(existing) = existing_manifest {
+        let (kept, dropped): (Vec<_>, Vec<_>) = existing
+            .images
+            .into_iter()
+            .filter(|img| !manifest.images.iter().any(|new| new.id == img.id))
+            .partition(|_| !prune);
+        if replace && !prune && !kept.is_empty() {
+            say!(json_output, "⚠ Some files failed, so {} image(s) no longer in the source set are kept for now\n", kept.len());
+        }
+        // Without --replace, uploading adds to the album; earlier images stay in front
+        manifest.images.splice(0..0, kept);
+        dropped_images = dropped;
+
+        // Keep a previously chosen cover as long as that image is still in the album
+        manifest.cover_image_id = existing
+            .cover_image_id
+            .filter(|id| manifest.images.iter().any(|img| &img.id == id));
+        manifest.password_hash = existing.password_hash;
+        manifest.description = manifest.description.or(existing.description);
+        manifest.event_date = manifest.event_date.or(existing.event_date);
+        // Slideshow settings not given again keep their current values; 0 and --no-slideshow-loop clear them
+        if slideshow_interval.is_none() {
+            manifest.slideshow_interval_secs = existing.slideshow_interval_secs;
+        }
+        if !no_slideshow_loop {
+            manifest.slideshow_loop = manifest.slideshow_loop.or(existing.slideshow_loop);
+        }
+        manifest.layout = manifest.layout.or(existing.layout);
+        // Theme settings not given again keep their current values
+        manifest.theme = match (manifest.theme.take(), existing.theme) {
+            (Some(theme), Some(existing)) => Some(Theme {
+                accent_color: theme.accent_color.or(existing.accent_color),
+                background_color: theme.background_color.or(existing.background_color),
+                font: theme.font.or(existing.font),
+            }),
+            (theme, existing) => theme.or(existing),
+        };
+        manifest.allow_download = allow_download || (manifest.allow_download && existing.allow_download);
+    }
+
+    // A new password replaces any existing protection
+    if password_hash.is_some() {
+        manifest.password_hash = password_hash;
+    }
+
+    if let OrderBy::CaptureDate = order_by {
+        manifest.sort_by_capture_date();
+    }
+
     // Upload manifest
-    let manifest_json = manifest.to_json()?;
-    let manifest_key = format!("{album_id}/manifest.json");
-    s3.upload_bytes(manifest_json.into_bytes(), &manifest_key, Some(manifest_expires))
-        .await?;
+    manifest.store_with(&s3, &manifest_options).await?;
+
+    if let Some(path) = &save_manifest {
+        fs::write(path, manifest.to_json()?)
+            .with_context(|| format!("Album uploaded, but failed to save its manifest to {}", path.display()))?;
+        say!(json_output, "✓ Saved manifest to {}\n", path.display());
+    }
+
+    // Only prune once the new manifest no longer references the dropped images
+    for image in &dropped_images {
+        for path in image.object_paths() {
+            s3.delete_object(&format!("{album_id}/{path}")).await?;
+        }
+    }
+    if !dropped_images.is_empty() {
+        say!(json_output, "✓ Removed {} image(s) no longer in the source set\n", dropped_images.len());
+    }
+    for path in &stale_paths {
+        s3.delete_object(&format!("{album_id}/{path}")).await?;
+    }
+    if !stale_paths.is_empty() {
+        say!(json_output, "✓ Removed {} object(s) replaced under new names\n", stale_paths.len());
+    }
+
+    let gallery_path = format!("/gallery/{album_id}");
+
+    if json_output {
+        let report = UploadReport {
+            album_id,
+            content_hash,
+            name: manifest.name.clone(),
+            total_images: manifest.images.len(),
+            uploaded: uploaded_count,
+            reused: reused_count,
+            removed: dropped_images.len(),
+            skipped,
+            failed: failed_uploads.clone(),
+            gallery_path,
+        };
+        println!("{}", serde_json::to_string(&report)?);
+        return upload_failure_result(&failed_uploads);
+    }
 
     println!("✓ Album complete!");
     println!("Album ID: {album_id}");
     println!("Total images: {}", manifest.images.len());
-    println!("\nAccess your gallery at: https://your-domain.com/gallery/{album_id}");
+
+    if !skipped.is_empty() {
+        println!("\n⚠ Skipped {} file(s) that couldn't be processed:", skipped.len());
+        for path in &skipped {
+            println!("  - {path}");
+        }
+    }
+    if !failed_uploads.is_empty() {
+        println!("\n⚠ Failed to upload {} file(s); they are not in the album:", failed_uploads.len());
+        for filename in &failed_uploads {
+            println!("  - {filename}");
+        }
+    }
+    println!("\nAccess your gallery at: https://your-domain.com{gallery_path}");
+
+    upload_failure_result(&failed_uploads)
+}
+
+/// Exit non-zero after a partial upload, once the manifest with the successful images is saved
+fn upload_failure_result(failed_uploads: &[String]) -> Result<()> {
+    if failed_uploads.is_empty() {
+        Ok(())
+    } else {
+        anyhow::bail!(
+            "{} image(s) failed to upload; run the same command again to retry them",
+            failed_uploads.len()
+        )
+    }
+}
+
+/// Hash every image and report what an upload would do, without writing to S3
+fn print_dry_run_plan(
+    album_id: &str,
+    hashed_images: &[(PathBuf, String)],
+    existing_images: &HashMap<String, ImageInfo>,
+    tiers: &[Tier],
+    overwrite: bool,
+    transcode_originals: bool,
+) -> Result<()> {
+    let mut reused = 0;
+    let mut to_upload = 0;
+
+    println!("Dry run - nothing will be written to S3\n");
+
+    for (path, file_hash) in hashed_images {
+        let existing = existing_images.get(file_hash);
+        let pending: Vec<Tier> = tiers
+            .iter()
+            .copied()
+            .filter(|&tier| overwrite || existing.is_none_or(|img| !img.has_tier(tier)))
+            .collect();
+        if let (Some(existing), true) = (existing, pending.is_empty()) {
+            reused += 1;
+            println!("  = {} (already uploaded as {})", path.display(), existing.id);
+            continue;
+        }
+
+        to_upload += 1;
+        // Real image IDs are assigned at upload time
+        let image_id = existing.map_or_else(|| Uuid::new_v4().to_string(), |img| img.id.clone());
+        println!("  + {}", path.display());
+        // HEIC sources and watermarked originals are stored as JPEG re-encodes, like the upload does
+        let extension = if transcode_originals || is_heic_file(path) {
+            "jpg".to_string()
+        } else {
+            ImageInfo::original_extension(&path.to_string_lossy())
+        };
+        for tier in pending {
+            match tier {
+                Tier::Original => println!("      {album_id}/originals/{image_id}.{extension}"),
+                Tier::Preview => println!("      {album_id}/previews/{image_id}.jpg"),
+                Tier::Thumbnail => println!("      {album_id}/thumbnails/{image_id}.jpg"),
+            }
+        }
+    }
+
+    println!(
+        "\nImages: {} total ({reused} already uploaded, {to_upload} to upload)",
+        hashed_images.len()
+    );
+    println!("Manifest: {}", AlbumManifest::key(album_id));
 
     Ok(())
 }
 
+/// Upload the selected tiers of one image. `existing` is the album's entry for an image that
+/// is getting tiers filled in or overwritten; its ID, caption and tags are kept.
+#[allow(clippy::too_many_arguments)]
 async fn upload_image_to_s3(
     s3: S3Client,
     album_id: String,
-    image_id: String,
     filename: String,
     file_hash: String,
+    existing: Option<ImageInfo>,
     processed: ProcessedImage,
-    expires: DateTime,
-) -> Result<ImageInfo> {
-    // Upload original
-    let original_key = format!("{album_id}/originals/{image_id}.jpg");
-    s3.upload_bytes(processed.original, &original_key, Some(expires)).await?;
-
-    // Upload preview
-    let preview_key = format!("{album_id}/previews/{image_id}.jpg");
-    s3.upload_bytes(processed.preview, &preview_key, Some(expires)).await?;
+    options: UploadOptions,
+    tiers: Vec<Tier>,
+) -> Result<(ImageInfo, Vec<String>)> {
+    let original_size_bytes = processed.original.len() as u64;
+    let original_transcoded = processed.original_transcoded;
+    let image_id = existing.as_ref().map_or_else(|| Uuid::new_v4().to_string(), |img| img.id.clone());
 
-    // Upload thumbnail
-    let thumbnail_key = format!("{album_id}/thumbnails/{image_id}.jpg");
-    s3.upload_bytes(processed.thumbnail, &thumbnail_key, Some(expires)).await?;
+    // A transcoded original is a JPEG; name it so downloads and exports open correctly
+    let filename = if processed.original_transcoded {
+        Path::new(&filename).with_extension("jpg").to_string_lossy().into_owned()
+    } else {
+        filename
+    };
 
-    Ok(ImageInfo::new(
+    let mut info = ImageInfo::new(
         filename,
         processed.width,
         processed.height,
         file_hash,
+        original_size_bytes,
         &album_id,
         &image_id,
-    ))
+    );
+
+    // Upload original, keeping the source file's extension
+    if tiers.contains(&Tier::Original) {
+        let original_key = format!("{album_id}/{}", info.original_path);
+        s3.upload_bytes_with(processed.original, &original_key, &options).await?;
+    }
+
+    // Upload preview
+    if tiers.contains(&Tier::Preview) {
+        let preview_key = format!("{album_id}/{}", info.preview_path);
+        s3.upload_bytes_with(processed.preview, &preview_key, &options).await?;
+    }
+
+    // Upload thumbnail
+    if tiers.contains(&Tier::Thumbnail) {
+        let thumbnail_key = format!("{album_id}/{}", info.thumbnail_path);
+        s3.upload_bytes_with(processed.thumbnail, &thumbnail_key, &options).await?;
+    }
+
+    // Optional AVIF tiers, uploaded along with their JPEG counterparts
+    let thumbnail_avif_path = match processed.thumbnail_avif.filter(|_| tiers.contains(&Tier::Thumbnail)) {
+        Some(data) => {
+            let path = format!("thumbnails/{image_id}.avif");
+            s3.upload_bytes_with(data, &format!("{album_id}/{path}"), &options).await?;
+            Some(path)
+        }
+        None => None,
+    };
+    let preview_avif_path = match processed.preview_avif.filter(|_| tiers.contains(&Tier::Preview)) {
+        Some(data) => {
+            let path = format!("previews/{image_id}.avif");
+            s3.upload_bytes_with(data, &format!("{album_id}/{path}"), &options).await?;
+            Some(path)
+        }
+        None => None,
+    };
+
+    // Smaller previews go with the preview tier; the full preview is listed as the widest variant
+    if tiers.contains(&Tier::Preview) && !processed.preview_variants.is_empty() {
+        for variant in processed.preview_variants {
+            let path = ImageInfo::preview_variant_path(&image_id, variant.size);
+            s3.upload_bytes_with(variant.data, &format!("{album_id}/{path}"), &options).await?;
+            info.preview_variants.push(PreviewVariant { width: variant.width, path, url: None });
+        }
+        info.preview_variants.push(PreviewVariant {
+            width: processed.preview_width,
+            path: info.preview_path.clone(),
+            url: None,
+        });
+    }
+
+    info.original_transcoded = original_transcoded;
+    info.exif = processed.exif;
+    info.orientation = processed.orientation;
+    info.thumbnail_avif_path = thumbnail_avif_path;
+    info.preview_avif_path = preview_avif_path;
+    info.lqip = Some(processed.lqip);
+    info.missing_tiers = Tier::ALL.into_iter().filter(|tier| !tiers.contains(tier)).collect();
+
+    let Some(mut existing) = existing else {
+        return Ok((info, Vec::new()));
+    };
+    let previous_paths: Vec<String> = existing.object_paths().into_iter().map(str::to_string).collect();
+
+    // Whatever was just uploaded describes the stored objects now; other tiers keep their entries
+    existing.missing_tiers.retain(|tier| !tiers.contains(tier));
+    if tiers.contains(&Tier::Original) {
+        existing.original_path = info.original_path;
+        existing.original_size_bytes = info.original_size_bytes;
+        existing.original_transcoded = info.original_transcoded;
+        existing.orientation = info.orientation;
+    }
+    if tiers.contains(&Tier::Thumbnail) {
+        existing.thumbnail_avif_path = info.thumbnail_avif_path;
+    }
+    if tiers.contains(&Tier::Preview) {
+        existing.preview_avif_path = info.preview_avif_path;
+        existing.preview_variants = info.preview_variants;
+    }
+    existing.width = info.width;
+    existing.height = info.height;
+    existing.exif = info.exif;
+    existing.lqip = info.lqip;
+
+    // Objects the updated entry no longer points at, e.g. an original stored under another
+    // extension or AVIF renditions from an earlier `--format avif`
+    let current_paths = existing.object_paths();
+    let stale_paths = previous_paths
+        .into_iter()
+        .filter(|path| !current_paths.contains(&path.as_str()))
+        .collect();
+
+    Ok((existing, stale_paths))
 }
 
-/// Compute a deterministic album ID from the set of image paths
-fn compute_album_id(image_paths: &[PathBuf]) -> String {
-    let mut hasher = Sha256::new();
+/// Abort before hashing if the upload is bigger than the given guards allow.
+/// Sizes come from filesystem metadata, so nothing is read yet.
+fn check_upload_size(image_paths: &[PathBuf], max_images: Option<usize>, max_total_mb: Option<u64>) -> Result<()> {
+    if let Some(max_images) = max_images {
+        if image_paths.len() > max_images {
+            anyhow::bail!(
+                "Found {} images, more than --max-images {max_images}; pass --force to upload anyway",
+                image_paths.len()
+            );
+        }
+    }
+
+    if let Some(max_total_mb) = max_total_mb {
+        let total_bytes: u64 = image_paths
+            .iter()
+            .map(|path| fs::metadata(path).map(|meta| meta.len()).unwrap_or(0))
+            .sum();
+        let total_mb = total_bytes.div_ceil(1024 * 1024);
+        if total_mb > max_total_mb {
+            anyhow::bail!(
+                "Images total {total_mb} MB, more than --max-total-mb {max_total_mb}; pass --force to upload anyway"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Name for an album uploaded without `--name`: the span of EXIF capture months
+/// ("2024-06" or "2024-06 — 2024-07"), else the folder the images share
+fn default_album_name<'a>(images: impl Iterator<Item = &'a ImageInfo>, image_paths: &[PathBuf]) -> String {
+    let mut months: Vec<String> = images
+        .filter_map(|img| img.exif.as_ref()?.date_taken.as_deref()?.get(..7).map(str::to_string))
+        .collect();
+    months.sort_unstable();
+
+    match (months.first(), months.last()) {
+        (Some(first), Some(last)) if first == last => first.clone(),
+        (Some(first), Some(last)) => format!("{first} — {last}"),
+        _ => common_directory_name(image_paths).unwrap_or_else(|| "Untitled album".to_string()),
+    }
+}
+
+/// Basename of the deepest directory containing every path
+fn common_directory_name(paths: &[PathBuf]) -> Option<String> {
+    let mut common = paths.first()?.parent()?.to_path_buf();
+    for path in &paths[1..] {
+        while !path.starts_with(&common) {
+            common.pop();
+        }
+    }
 
-    // Hash the sorted list of image paths (canonicalized representations)
-    // This ensures the same set of images always produces the same ID
-    for path in image_paths {
-        // Use the path as a string for hashing
-        hasher.update(path.to_string_lossy().as_bytes());
+    // Relative paths like `photo.jpg` or `./trip` need resolving to have a name
+    if common.as_os_str().is_empty() {
+        common = PathBuf::from(".");
+    }
+    let common = common.canonicalize().ok()?;
+    common.file_name().map(utf8_name)
+}
+
+/// A file or folder name fit for the manifest and download headers. Names that aren't valid
+/// UTF-8 would carry replacement characters, so they are reduced to ASCII with a warning.
+fn utf8_name(name: &OsStr) -> String {
+    if let Some(name) = name.to_str() {
+        return name.to_string();
+    }
+
+    let sanitized: String = name
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_graphic() || c == ' ' { c } else { '_' })
+        .collect();
+    tracing::warn!("{:?} is not valid UTF-8; recording it as {:?}", name, sanitized);
+    sanitized
+}
+
+/// Compute a deterministic album ID from the content hashes of its images
+fn compute_album_id<'a>(file_hashes: impl Iterator<Item = &'a str>) -> String {
+    // Sort so the ID doesn't depend on file names or the order they were found in
+    let mut file_hashes: Vec<_> = file_hashes.collect();
+    file_hashes.sort_unstable();
+    file_hashes.dedup();
+
+    let mut hasher = Sha256::new();
+    for file_hash in file_hashes {
+        hasher.update(file_hash.as_bytes());
         hasher.update(b"\n"); // Separator
     }
 
@@ -257,46 +1108,149 @@ fn compute_album_id(image_paths: &[PathBuf]) -> String {
     format!("{result:x}")[..16].to_string() // Use first 16 chars
 }
 
-/// Hash a file's content
+/// Run parallel work on the `--jobs` pool, or on rayon's global pool without one
+fn run_in<R: Send>(pool: Option<&rayon::ThreadPool>, work: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(work),
+        None => work(),
+    }
+}
+
+/// Keep successful results; failures are recorded in `skipped` with a warning,
+/// or abort the upload when `fail_fast` is set
+fn keep_successes<T>(
+    outcomes: Vec<(&PathBuf, Result<T>)>,
+    fail_fast: bool,
+    skipped: &mut Vec<String>,
+) -> Result<Vec<T>> {
+    let mut successes = Vec::with_capacity(outcomes.len());
+
+    for (path, outcome) in outcomes {
+        match outcome {
+            Ok(value) => successes.push(value),
+            Err(e) if fail_fast => return Err(e.context(format!("Failed to process {}", path.display()))),
+            Err(e) => {
+                tracing::warn!("Skipping {}: {:#}", path.display(), e);
+                skipped.push(path.display().to_string());
+            }
+        }
+    }
+
+    Ok(successes)
+}
+
+/// Hash a file's content, streaming it through a fixed-size buffer
 fn hash_file(path: &Path) -> Result<String> {
-    let file_content = fs::read(path)?;
+    let mut reader = BufReader::new(fs::File::open(path)?);
     let mut hasher = Sha256::new();
-    hasher.update(&file_content);
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
     let result = hasher.finalize();
     Ok(format!("{result:x}"))
 }
 
-fn collect_image_paths(paths: Vec<String>) -> Result<Vec<PathBuf>> {
+/// How directories given on the command line are searched
+#[derive(Clone, Copy)]
+struct WalkOptions {
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+}
+
+fn collect_image_paths(paths: Vec<String>, walk: WalkOptions) -> Result<Vec<PathBuf>> {
     let mut image_paths = Vec::new();
 
     for path_str in paths {
-        let path = Path::new(&path_str);
+        for path in expand_path_arg(&path_str)? {
+            collect_from_path(&path, walk, &mut image_paths)?;
+        }
+    }
 
-        if !path.exists() {
-            anyhow::bail!("Path does not exist: {}", path.display());
+    // Sort for consistent ordering; overlapping arguments may list a file twice
+    image_paths.sort();
+    image_paths.dedup();
+
+    Ok(image_paths)
+}
+
+/// Expand a quoted glob like `photos/2024-*/*.jpg`; plain paths pass through untouched
+fn expand_path_arg(path_str: &str) -> Result<Vec<PathBuf>> {
+    if !path_str.contains(['*', '?', '[']) {
+        return Ok(vec![PathBuf::from(path_str)]);
+    }
+
+    let matches = glob::glob(path_str)
+        .map_err(|e| anyhow::anyhow!("Invalid glob pattern {path_str:?}: {e}"))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if matches.is_empty() {
+        anyhow::bail!("No files matched pattern: {path_str}");
+    }
+
+    Ok(matches)
+}
+
+fn collect_from_path(path: &Path, walk: WalkOptions, image_paths: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.exists() {
+        anyhow::bail!("Path does not exist: {}", path.display());
+    }
+
+    if path.is_file() {
+        if is_image_file(path) {
+            image_paths.push(path.to_path_buf());
+        }
+    } else if path.is_dir() {
+        // Walk directory and collect all images
+        let mut walker = WalkDir::new(path).follow_links(walk.follow_symlinks);
+        if let Some(depth) = walk.max_depth {
+            walker = walker.max_depth(depth);
         }
 
-        if path.is_file() {
-            if is_image_file(path) {
-                image_paths.push(path.to_path_buf());
-            }
-        } else if path.is_dir() {
-            // Walk directory and collect all images
-            for entry in WalkDir::new(path)
-                .follow_links(true)
-                .into_iter()
-                .filter_map(|e| e.ok())
-            {
-                let entry_path = entry.path();
-                if entry_path.is_file() && is_image_file(entry_path) {
-                    image_paths.push(entry_path.to_path_buf());
-                }
+        for entry in walker.into_iter().filter_map(|e| {
+            // Unreadable entries and symlink loops are skipped rather than aborting the walk
+            e.map_err(|err| tracing::warn!("Skipping {}", err)).ok()
+        }) {
+            // The entry's own type: a symlink only counts as a file when --follow-symlinks resolved it
+            let entry_path = entry.path();
+            if entry.file_type().is_file() && is_image_file(entry_path) {
+                image_paths.push(entry_path.to_path_buf());
             }
         }
     }
 
-    // Sort for consistent ordering
-    image_paths.sort();
+    Ok(())
+}
 
-    Ok(image_paths)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reupload_without_expires_in_days_keeps_album_expiry() {
+        // First upload with `--expires-in-days 7`
+        let expires_at = album_expires_at(Some(7), None).unwrap();
+        let until = expires_at - Utc::now();
+        assert!(until > Duration::days(7) - Duration::minutes(1) && until <= Duration::days(7));
+
+        let mut manifest = AlbumManifest::with_id("Expiring".to_string(), "album".to_string());
+        manifest.expires_at = Some(expires_at.to_rfc3339());
+
+        // Re-upload into it without the flag
+        let kept = album_expires_at(None, Some(&manifest)).unwrap();
+        assert_eq!(kept.timestamp(), expires_at.timestamp());
+        assert_eq!(kept.to_rfc3339(), expires_at.to_rfc3339());
+
+        // The flag still replaces it, and albums without one stay permanent
+        assert!(album_expires_at(Some(30), Some(&manifest)).unwrap() > expires_at);
+        manifest.expires_at = None;
+        assert_eq!(album_expires_at(None, Some(&manifest)), None);
+        assert_eq!(album_expires_at(None, None), None);
+    }
 }
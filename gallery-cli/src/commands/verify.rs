@@ -0,0 +1,43 @@
+use anyhow::Result;
+use gallery_core::AlbumManifest;
+
+use super::S3Args;
+
+pub async fn execute(album_id: String, s3_args: S3Args) -> Result<()> {
+    tracing::info!("Verifying album: {}", album_id);
+
+    // Initialize S3 client
+    let s3 = s3_args.connect().await?;
+
+    let manifest = AlbumManifest::fetch(&s3, &album_id).await?;
+
+    // Check every tier of every image listed in the manifest
+    let mut missing = Vec::new();
+    let mut total = 0;
+    for image in &manifest.images {
+        for path in image.object_paths() {
+            total += 1;
+            let key = format!("{album_id}/{path}");
+            if !s3.object_exists(&key).await? {
+                println!("✗ Missing: {key} ({})", image.original_filename);
+                missing.push(key);
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Album {album_id} is incomplete: {} of {} objects missing",
+            missing.len(),
+            total
+        );
+    }
+
+    println!("✓ Album verified: {} images, all objects present", manifest.images.len());
+    let pending = manifest.images.iter().filter(|img| !img.missing_tiers.is_empty()).count();
+    if pending > 0 {
+        println!("  {pending} image(s) still have tiers left out with --tiers");
+    }
+
+    Ok(())
+}
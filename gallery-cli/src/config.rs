@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE_NAME: &str = "gallery.toml";
+
+/// Defaults read from `gallery.toml`; flags and environment variables take precedence
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub bucket: Option<String>,
+    /// Separate bucket for full-size originals
+    pub originals_bucket: Option<String>,
+    /// Namespace for albums within the bucket
+    pub prefix: Option<String>,
+    pub region: Option<String>,
+    pub endpoint_url: Option<String>,
+    /// Named AWS profile to take credentials from
+    pub profile: Option<String>,
+    /// Maximum number of images uploaded at once
+    pub concurrency: Option<usize>,
+    /// Longest edge of grid thumbnails, in pixels
+    pub thumbnail_size: Option<u32>,
+    /// Longest edge of lightbox previews, in pixels
+    pub preview_size: Option<u32>,
+}
+
+impl Config {
+    /// Load an explicit config file, or the first of `./gallery.toml` and
+    /// `$HOME/.config/gallery/gallery.toml` that exists (empty defaults if neither does)
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        if let Some(path) = explicit {
+            return Self::from_file(path);
+        }
+
+        let candidates = [
+            Some(PathBuf::from(CONFIG_FILE_NAME)),
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".config/gallery").join(CONFIG_FILE_NAME)),
+        ];
+
+        match candidates.into_iter().flatten().find(|path| path.is_file()) {
+            Some(path) => Self::from_file(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        tracing::debug!("Loading config from {}", path.display());
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+}
@@ -1,82 +1,434 @@
 use anyhow::{Context, Result};
-use image::{imageops::FilterType, DynamicImage, GenericImageView, ImageFormat};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use clap::ValueEnum;
+use exif::{In, Reader, Tag, Value};
+use gallery_core::ExifData;
+use image::{codecs::avif::AvifEncoder, imageops::FilterType, DynamicImage, GenericImageView, ImageDecoder};
+use jpeg_encoder::{ColorType, Encoder};
 use std::fs;
 use std::io::Cursor;
 use std::path::Path;
+use std::sync::Arc;
+
+use crate::watermark::Watermark;
 
 pub struct ProcessedImage {
     pub original: Vec<u8>,
     pub preview: Vec<u8>,
     pub thumbnail: Vec<u8>,
+    pub preview_avif: Option<Vec<u8>>,
+    pub thumbnail_avif: Option<Vec<u8>>,
+    /// Smaller previews for `srcset`, when `ProcessOptions::responsive` is set
+    pub preview_variants: Vec<EncodedVariant>,
+    /// Width of `preview` in pixels
+    pub preview_width: u32,
+    /// Placeholder `data:` URI small enough to inline in the manifest
+    pub lqip: String,
     pub width: u32,
     pub height: u32,
+    pub exif: Option<ExifData>,
+    /// EXIF orientation of `original` when it isn't upright (re-encodes carry no EXIF).
+    /// Everything else, `width` and `height` included, is already upright
+    pub orientation: Option<u16>,
+    /// The source format can't be shown by browsers, so `original` holds a JPEG re-encode
+    pub original_transcoded: bool,
+}
+
+/// A downscaled preview for a given longest-edge size
+pub struct EncodedVariant {
+    /// Nominal longest edge, used in the object name
+    pub size: u32,
+    /// Actual width in pixels
+    pub width: u32,
+    pub data: Vec<u8>,
+}
+
+pub const THUMBNAIL_SIZE: u32 = 400;
+pub const PREVIEW_SIZE: u32 = 2048;
+pub const THUMBNAIL_QUALITY: u8 = 85;
+pub const PREVIEW_QUALITY: u8 = 90;
+
+/// Longest edges of the extra previews generated for `srcset`; sizes not below the preview size are skipped
+const RESPONSIVE_PREVIEW_SIZES: [u32; 2] = [640, 1280];
+
+/// Longest edge of the inline placeholder; browsers blur it when scaling up
+const LQIP_SIZE: u32 = 20;
+const LQIP_QUALITY: u8 = 40;
+
+/// Quality of the JPEG stored in place of originals that were re-encoded (HEIC, watermarked)
+const TRANSCODED_ORIGINAL_QUALITY: u8 = 95;
+
+/// AVIF quality; roughly matches the JPEG tiers visually at a fraction of the size
+const AVIF_QUALITY: u8 = 80;
+
+/// Resampling filter used when downscaling to the preview and thumbnail sizes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ResizeFilter {
+    /// Sharpest results, slowest
+    #[default]
+    Lanczos3,
+    /// Bilinear; much faster, slightly softer
+    Triangle,
+    /// Bicubic; close to Lanczos3 with less ringing
+    #[value(name = "catmullrom")]
+    CatmullRom,
+    /// Smooth, soft results
+    Gaussian,
+    /// Fastest, blocky; mostly for testing
+    Nearest,
 }
 
-const THUMBNAIL_SIZE: u32 = 400;
-const PREVIEW_SIZE: u32 = 2048;
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Nearest => FilterType::Nearest,
+        }
+    }
+}
 
-pub fn process_image(path: &Path) -> Result<ProcessedImage> {
-    tracing::info!("Processing image: {}", path.display());
+/// How the derived tiers are generated
+#[derive(Clone, Debug)]
+pub struct ProcessOptions {
+    /// Longest edge of thumbnails, in pixels
+    pub thumbnail_size: u32,
+    /// Longest edge of previews, in pixels
+    pub preview_size: u32,
+    /// JPEG quality (1-100) of each tier
+    pub thumbnail_quality: u8,
+    pub preview_quality: u8,
+    /// Filter used for every downscale
+    pub resize_filter: ResizeFilter,
+    /// Also encode AVIF tiers at this encoder speed (1 = slowest/smallest, 10 = fastest)
+    pub avif_speed: Option<u8>,
+    /// Encode previews as progressive JPEGs so the lightbox shows a full frame early
+    pub progressive_previews: bool,
+    /// Overlay applied to previews, and to originals if the watermark asks for it
+    pub watermark: Option<Arc<Watermark>>,
+    /// Also encode smaller previews so browsers can pick a size that fits the screen
+    pub responsive: bool,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            thumbnail_size: THUMBNAIL_SIZE,
+            preview_size: PREVIEW_SIZE,
+            thumbnail_quality: THUMBNAIL_QUALITY,
+            preview_quality: PREVIEW_QUALITY,
+            resize_filter: ResizeFilter::default(),
+            avif_speed: None,
+            progressive_previews: true,
+            watermark: None,
+            responsive: false,
+        }
+    }
+}
+
+pub fn process_image(path: &Path, options: &ProcessOptions) -> Result<ProcessedImage> {
+    tracing::debug!("Processing image: {}", path.display());
+
+    #[cfg(feature = "heic")]
+    if is_heic_file(path) {
+        let source = fs::read(path)
+            .context(format!("Failed to read original file: {}", path.display()))?;
+        return process_heic(&source, options)
+            .with_context(|| format!("Failed to process image: {}", path.display()));
+    }
 
     // Verify file is JPEG
     if !is_jpeg_file(path) {
+        if is_heic_file(path) {
+            anyhow::bail!(
+                "HEIC support is not enabled in this build (rebuild with `--features heic`). Got: {}",
+                path.display()
+            );
+        }
         anyhow::bail!(
             "Only JPEG files (.jpg, .jpeg) are supported. Got: {}",
             path.display()
         );
     }
 
-    // Load the image to get dimensions and create variants
-    let img = image::open(path)
-        .context(format!("Failed to open image: {}", path.display()))?;
-
-    let (width, height) = img.dimensions();
-
     // Read original file as-is (no re-encoding to preserve quality)
     let original = fs::read(path)
         .context(format!("Failed to read original file: {}", path.display()))?;
 
-    // Create preview (2048px max dimension) - for lightbox initial load
-    let preview = create_resized_jpeg(&img, PREVIEW_SIZE, 90)?;
+    process_original(original, options)
+        .with_context(|| format!("Failed to process image: {}", path.display()))
+}
+
+/// Generate the derived tiers from an original's bytes, e.g. one downloaded from S3
+pub fn process_original(original: Vec<u8>, options: &ProcessOptions) -> Result<ProcessedImage> {
+    // The decoder fills a cut-off scan with gray instead of failing, so check the file is complete first
+    if !has_end_of_image(&original) {
+        anyhow::bail!("JPEG is truncated (no end-of-image marker); re-export or re-copy it from the source");
+    }
+
+    // Load the image to get dimensions and create variants, turned upright: the original is
+    // served with its EXIF orientation, but the re-encoded tiers carry no EXIF to apply it
+    let mut decoder = image::ImageReader::new(Cursor::new(&original))
+        .with_guessed_format()
+        .context("Failed to read image")?
+        .into_decoder()
+        .context("Failed to decode image")?;
+    let orientation = decoder.orientation().context("Failed to read image orientation")?;
+    let mut img = DynamicImage::from_decoder(decoder).context("Failed to decode image")?;
+    img.apply_orientation(orientation);
 
-    // Create thumbnail (400px max dimension) - for grid
-    let thumbnail = create_resized_jpeg(&img, THUMBNAIL_SIZE, 85)?;
+    let exif = read_exif(&original);
+
+    derive_tiers(img, original, exif, false, options)
+}
+
+/// Whether a JPEG's image data runs to its end-of-image marker (`FF D9`).
+/// Segments are skipped up to the first scan so an embedded EXIF thumbnail's marker doesn't count;
+/// within scan data `FF` bytes are stuffed, so `FF D9` there can only be the real marker. Data
+/// appended after it (e.g. motion photo videos) is allowed.
+fn has_end_of_image(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+
+    let mut pos = 2;
+    while let Some(&[0xFF, marker, len_hi, len_lo]) = data.get(pos..pos + 4) {
+        // Markers may be preceded by any number of fill bytes
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let segment_end = pos + 2 + usize::from(u16::from_be_bytes([len_hi, len_lo]));
+        if marker == 0xDA {
+            return data
+                .get(segment_end..)
+                .is_some_and(|scan| scan.windows(2).any(|pair| pair == [0xFF, 0xD9]));
+        }
+        pos = segment_end;
+    }
+    false
+}
+
+/// Decode a HEIC/HEIF file and store a JPEG re-encode as its original
+#[cfg(feature = "heic")]
+fn process_heic(source: &[u8], options: &ProcessOptions) -> Result<ProcessedImage> {
+    let img = decode_heic(source)?;
+    // kamadak-exif reads the EXIF block straight from the HEIF container
+    let exif = read_exif(source);
+    let original = encode_jpeg(&img, TRANSCODED_ORIGINAL_QUALITY, false)?;
+
+    derive_tiers(img, original, exif, true, options)
+}
+
+#[cfg(feature = "heic")]
+fn decode_heic(source: &[u8]) -> Result<DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let context = HeifContext::read_from_bytes(source).context("Failed to read HEIC container")?;
+    let handle = context.primary_image_handle().context("HEIC file has no primary image")?;
+    // Decoding applies the container's rotation and mirroring
+    let decoded = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .context("Failed to decode HEIC")?;
+
+    let planes = decoded.planes();
+    let plane = planes.interleaved.context("HEIC decoder returned no RGB plane")?;
+
+    // Rows may be padded; copy out just the pixels
+    let row_len = plane.width as usize * 3;
+    let mut pixels = Vec::with_capacity(row_len * plane.height as usize);
+    for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+        pixels.extend_from_slice(&row[..row_len]);
+    }
+
+    let rgb = image::RgbImage::from_raw(plane.width, plane.height, pixels)
+        .context("HEIC pixel data has an unexpected size")?;
+    Ok(DynamicImage::ImageRgb8(rgb))
+}
+
+/// Build the preview, thumbnail and placeholder for a decoded, upright image
+fn derive_tiers(
+    img: DynamicImage,
+    original: Vec<u8>,
+    exif: Option<ExifData>,
+    original_transcoded: bool,
+    options: &ProcessOptions,
+) -> Result<ProcessedImage> {
+    let (width, height) = img.dimensions();
+
+    // `img` is already upright, so the mark lands in the corner viewers see and the re-encode,
+    // which carries no EXIF, needs no orientation
+    let (original, original_transcoded) = match &options.watermark {
+        Some(watermark) if watermark.apply_to_original => {
+            (encode_jpeg(&watermark.apply(&img), TRANSCODED_ORIGINAL_QUALITY, false)?, true)
+        }
+        _ => (original, original_transcoded),
+    };
+    let orientation = if original_transcoded { None } else { read_orientation(&original) };
+
+    // Create preview (2048px max dimension by default) - for lightbox initial load
+    let mut preview_img = resize_to_fit(&img, options.preview_size, options.resize_filter);
+    if let Some(watermark) = &options.watermark {
+        preview_img = watermark.apply(&preview_img);
+    }
+    let preview = encode_jpeg(&preview_img, options.preview_quality, options.progressive_previews)?;
+
+    let preview_longest_edge = preview_img.width().max(preview_img.height());
+    let preview_variants = if options.responsive {
+        RESPONSIVE_PREVIEW_SIZES
+            .into_iter()
+            .filter(|&size| size < preview_longest_edge)
+            .map(|size| {
+                let variant = resize_to_fit(&preview_img, size, options.resize_filter);
+                Ok(EncodedVariant {
+                    size,
+                    width: variant.width(),
+                    data: encode_jpeg(&variant, options.preview_quality, options.progressive_previews)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+
+    // Create thumbnail (400px max dimension by default) - for grid; baseline since they're tiny
+    let thumbnail_img = resize_to_fit(&img, options.thumbnail_size, options.resize_filter);
+    let thumbnail = encode_jpeg(&thumbnail_img, options.thumbnail_quality, false)?;
+
+    let lqip_img = resize_to_fit(&thumbnail_img, LQIP_SIZE, options.resize_filter);
+    let lqip = format!("data:image/jpeg;base64,{}", BASE64.encode(encode_jpeg(&lqip_img, LQIP_QUALITY, false)?));
+
+    let (preview_avif, thumbnail_avif) = match options.avif_speed {
+        Some(speed) => (
+            Some(encode_avif(&preview_img, speed)?),
+            Some(encode_avif(&thumbnail_img, speed)?),
+        ),
+        None => (None, None),
+    };
 
     Ok(ProcessedImage {
         original,
         preview,
         thumbnail,
+        preview_avif,
+        thumbnail_avif,
+        preview_variants,
+        preview_width: preview_img.width(),
+        lqip,
         width,
         height,
+        exif,
+        orientation,
+        original_transcoded,
+    })
+}
+
+/// The original's EXIF orientation tag, if it asks viewers to rotate or mirror the image (2-8)
+fn read_orientation(original: &[u8]) -> Option<u16> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(original))
+        .ok()?;
+
+    let orientation = exif.get_field(Tag::Orientation, In::PRIMARY)?.value.get_uint(0)?;
+    u16::try_from(orientation).ok().filter(|value| (2..=8).contains(value))
+}
+
+/// Extract shooting metadata from the original file, if it carries EXIF
+fn read_exif(original: &[u8]) -> Option<ExifData> {
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(original))
+        .ok()?;
+
+    let field = |tag| exif.get_field(tag, In::PRIMARY).map(|f| &f.value);
+
+    let ascii = |tag| match field(tag)? {
+        Value::Ascii(values) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).trim().to_string())
+            .filter(|v| !v.is_empty()),
+        _ => None,
+    };
+
+    let rational = |tag| match field(tag)? {
+        Value::Rational(values) => values.first().copied(),
+        _ => None,
+    };
+
+    let date_taken = match field(Tag::DateTimeOriginal) {
+        Some(Value::Ascii(values)) => values
+            .first()
+            .and_then(|v| exif::DateTime::from_ascii(v).ok())
+            .map(|dt| {
+                format!(
+                    "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                    dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+                )
+            }),
+        _ => None,
+    };
+
+    let exposure_time = rational(Tag::ExposureTime).map(|r| {
+        if r.num > 0 && r.num < r.denom {
+            format!("1/{}", (r.denom as f64 / r.num as f64).round())
+        } else {
+            format!("{}", r.to_f64())
+        }
+    });
+
+    Some(ExifData {
+        camera_make: ascii(Tag::Make),
+        camera_model: ascii(Tag::Model),
+        iso: field(Tag::PhotographicSensitivity).and_then(|v| v.get_uint(0)),
+        f_number: rational(Tag::FNumber).map(|r| r.to_f64()),
+        exposure_time,
+        focal_length: rational(Tag::FocalLength).map(|r| r.to_f64()),
+        date_taken,
     })
 }
 
-fn create_resized_jpeg(img: &DynamicImage, max_size: u32, quality: u8) -> Result<Vec<u8>> {
+fn resize_to_fit(img: &DynamicImage, max_size: u32, filter: ResizeFilter) -> DynamicImage {
     let (width, height) = img.dimensions();
 
     // Only resize if larger than target
-    let resized = if width > max_size || height > max_size {
-        img.resize(max_size, max_size, FilterType::Lanczos3)
+    if width > max_size || height > max_size {
+        img.resize(max_size, max_size, filter.filter_type())
     } else {
         img.clone()
-    };
-
-    encode_jpeg(&resized, quality)
+    }
 }
 
-fn encode_jpeg(img: &DynamicImage, _quality: u8) -> Result<Vec<u8>> {
-    let mut buffer = Cursor::new(Vec::new());
+fn encode_jpeg(img: &DynamicImage, quality: u8, progressive: bool) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+
+    let rgb = img.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    let width = u16::try_from(width).context("Image too wide to encode as JPEG")?;
+    let height = u16::try_from(height).context("Image too tall to encode as JPEG")?;
 
-    img.write_to(&mut buffer, ImageFormat::Jpeg)
+    let mut encoder = Encoder::new(&mut buffer, quality);
+    encoder.set_progressive(progressive);
+    encoder
+        .encode(rgb.as_raw(), width, height, ColorType::Rgb)
         .context("Failed to encode JPEG")?;
 
-    // TODO: Use quality parameter with a JPEG encoder that supports it
-    // For now, using standard image crate encoding with default quality
-    Ok(buffer.into_inner())
+    Ok(buffer)
+}
+
+fn encode_avif(img: &DynamicImage, speed: u8) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+
+    // The encoder only accepts 8-bit RGB(A)
+    let rgb = DynamicImage::ImageRgb8(img.to_rgb8());
+    rgb.write_with_encoder(AvifEncoder::new_with_speed_quality(&mut buffer, speed, AVIF_QUALITY))
+        .context("Failed to encode AVIF")?;
+
+    Ok(buffer)
 }
 
 pub fn is_image_file(path: &Path) -> bool {
-    is_jpeg_file(path)
+    is_jpeg_file(path) || (cfg!(feature = "heic") && is_heic_file(path))
 }
 
 pub fn is_jpeg_file(path: &Path) -> bool {
@@ -89,3 +441,83 @@ pub fn is_jpeg_file(path: &Path) -> bool {
         false
     }
 }
+
+pub fn is_heic_file(path: &Path) -> bool {
+    if let Some(ext) = path.extension() {
+        matches!(
+            ext.to_str().unwrap_or("").to_lowercase().as_str(),
+            "heic" | "heif"
+        )
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 60x30 JPEG, red on its left third and blue elsewhere, tagged to be shown rotated 90° clockwise
+    fn rotated_jpeg() -> Vec<u8> {
+        let img = image::RgbImage::from_fn(60, 30, |x, _| if x < 20 { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) });
+        let jpeg = encode_jpeg(&DynamicImage::ImageRgb8(img), 95, false).unwrap();
+
+        // APP1 with a big-endian TIFF header and a single IFD entry: Orientation (SHORT) = 6
+        let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0, 0, 0, 1, 0x00, 0x06, 0, 0]);
+        tiff.extend_from_slice(&[0, 0, 0, 0]);
+        let mut app1 = b"Exif\0\0".to_vec();
+        app1.extend_from_slice(&tiff);
+
+        let mut tagged = jpeg[..2].to_vec();
+        tagged.extend_from_slice(&[0xFF, 0xE1]);
+        tagged.extend_from_slice(&u16::try_from(app1.len() + 2).unwrap().to_be_bytes());
+        tagged.extend_from_slice(&app1);
+        tagged.extend_from_slice(&jpeg[2..]);
+        tagged
+    }
+
+    fn is_red(pixel: image::Rgb<u8>) -> bool {
+        pixel[0] > 200 && pixel[2] < 60
+    }
+
+    #[test]
+    fn derived_tiers_are_upright() {
+        let original = rotated_jpeg();
+        let processed = process_original(original.clone(), &ProcessOptions::default()).unwrap();
+
+        // The original is stored as-is, for viewers to rotate
+        assert_eq!(processed.original, original);
+        assert_eq!(processed.orientation, Some(6));
+        assert_eq!((processed.width, processed.height), (30, 60));
+
+        for tier in [&processed.preview, &processed.thumbnail] {
+            let tier = image::load_from_memory(tier).unwrap().to_rgb8();
+            assert_eq!(tier.dimensions(), (30, 60));
+            // Turned clockwise, the red left third ends up on top
+            assert!(is_red(*tier.get_pixel(15, 5)));
+            assert!(!is_red(*tier.get_pixel(15, 55)));
+        }
+    }
+
+    #[test]
+    fn watermarked_originals_are_upright() {
+        let mark_path = std::env::temp_dir().join(format!("gallery-test-mark-{}.png", std::process::id()));
+        image::RgbaImage::from_pixel(4, 4, image::Rgba([0, 255, 0, 255])).save(&mark_path).unwrap();
+        let watermark = Watermark::load(&mark_path, Default::default(), 1.0, true).unwrap();
+        fs::remove_file(&mark_path).unwrap();
+
+        let options = ProcessOptions { watermark: Some(Arc::new(watermark)), ..Default::default() };
+        let processed = process_original(rotated_jpeg(), &options).unwrap();
+
+        // Re-encoded without EXIF, so it has to be stored the way it is meant to be seen
+        assert!(processed.original_transcoded);
+        assert_eq!(processed.orientation, None);
+        let original = image::load_from_memory(&processed.original).unwrap().to_rgb8();
+        assert_eq!(original.dimensions(), (30, 60));
+        assert!(is_red(*original.get_pixel(15, 5)));
+        // The mark sits in the bottom-right corner of the upright image
+        let mark = original.get_pixel(26, 57);
+        assert!(mark[1] > 200 && mark[0] < 60, "no watermark in the corner: {mark:?}");
+    }
+}
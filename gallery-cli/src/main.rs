@@ -1,14 +1,25 @@
 mod commands;
+mod config;
 mod image_processor;
+mod watermark;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use commands::reprocess::ReprocessArgs;
+use commands::upload::{OutputFormat, UploadArgs};
+use commands::S3Args;
+use config::Config;
+use std::path::PathBuf;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
 #[command(name = "gallery")]
 #[command(about = "Film gallery CLI tool for S3-based photo management", long_about = None)]
 struct Cli {
+    /// Config file to use instead of ./gallery.toml or ~/.config/gallery/gallery.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -16,54 +27,294 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Upload images to create a new album
-    Upload {
-        /// Directory or files to upload
-        #[arg(required = true)]
-        paths: Vec<String>,
+    Upload(Box<UploadArgs>),
 
-        /// Album name
-        #[arg(short, long)]
-        name: String,
+    /// Delete an album
+    Delete {
+        /// Album ID to delete
+        album_id: String,
 
-        /// S3 bucket name
-        #[arg(short, long, env = "GALLERY_BUCKET")]
-        bucket: String,
+        #[command(flatten)]
+        s3: S3Args,
+    },
 
-        /// Days until gallery expires (default: 7)
-        #[arg(long, default_value = "7")]
-        expires_in_days: u32,
+    /// Remove a single image from an album
+    Remove {
+        /// Album ID containing the image
+        album_id: String,
+
+        /// Image ID to remove
+        image_id: String,
+
+        #[command(flatten)]
+        s3: S3Args,
     },
 
-    /// Delete an album
-    Delete {
-        /// Album ID to delete
+    /// Change an album's display name
+    Rename {
+        /// Album ID to rename
         album_id: String,
 
-        /// S3 bucket name
-        #[arg(short, long, env = "GALLERY_BUCKET")]
-        bucket: String,
+        /// New album name
+        new_name: String,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Set or clear the caption shown under an image
+    Caption {
+        /// Album ID containing the image
+        album_id: String,
+
+        /// Image ID to caption
+        image_id: String,
+
+        /// Caption text (empty to clear)
+        text: String,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Replace an image's tags, which viewers can filter the gallery by (no tags clears them)
+    Tag {
+        /// Album ID containing the image
+        album_id: String,
+
+        /// Image ID to tag
+        image_id: String,
+
+        /// Tags such as `portrait` or `bw`
+        tags: Vec<String>,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Choose the image representing the album in listings and link previews
+    SetCover {
+        /// Album ID to update
+        album_id: String,
+
+        /// Image ID to use as the cover
+        image_id: String,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Change the order images are shown in
+    Reorder {
+        /// Album ID to reorder
+        album_id: String,
+
+        /// Every image ID of the album, comma-separated, in the new display order
+        #[arg(long, value_delimiter = ',', required = true)]
+        order: Vec<String>,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Download an album's originals and manifest to a local directory
+    Export {
+        /// Album ID to export
+        album_id: String,
+
+        /// Directory to write the images into (created if missing)
+        dest_dir: PathBuf,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Write an album as a self-contained static site (index.html plus every image tier)
+    StaticExport {
+        /// Album ID to export
+        album_id: String,
+
+        /// Directory to write the site into (created if missing)
+        dest_dir: PathBuf,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Regenerate an album's previews and thumbnails from its originals at current settings
+    Reprocess(ReprocessArgs),
+
+    /// Install a bucket lifecycle rule that expires album objects after a number of days
+    SetupLifecycle {
+        /// Days after upload before S3 deletes an object
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        days: u32,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Push back the expiry of an album uploaded with --expires-in-days
+    Extend {
+        /// Album ID to extend
+        album_id: String,
+
+        /// Days to add to the current expiry (counted from now if it has already passed)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        days: u32,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Delete objects under an album's prefix that its manifest doesn't reference
+    PruneOrphans {
+        /// Album ID to clean up
+        album_id: String,
+
+        /// List the orphaned objects without deleting them
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Embed freshly presigned image URLs in an album's manifest, for serving it as a static file
+    RefreshUrls {
+        /// Album ID to refresh
+        album_id: String,
+
+        /// Days the URLs stay valid (S3 allows at most 7)
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u32).range(1..=7))]
+        expires_days: u32,
+
+        /// Write the manifest to this local file instead of back to S3
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Print a gallery link that stops working after a while (needs GALLERY_SIGNING_KEY on the web app)
+    SignUrl {
+        /// Album ID to link to
+        album_id: String,
+
+        /// Environment variable holding the signing secret
+        #[arg(long, default_value = "GALLERY_SIGNING_KEY")]
+        secret_env: String,
+
+        /// Seconds until the link expires
+        #[arg(long, default_value_t = 86400, value_parser = clap::value_parser!(u64).range(1..))]
+        expires: u64,
+    },
+
+    /// List images stored in more than one album and the space duplicates take up
+    DuplicateReport {
+        /// Print a human-readable report or a single JSON object
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Summarize the bucket's storage use by tier
+    Stats {
+        /// Print a human-readable summary or a single JSON object
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+
+        #[command(flatten)]
+        s3: S3Args,
+    },
+
+    /// Check that every image in an album has all its S3 objects
+    Verify {
+        /// Album ID to verify
+        album_id: String,
+
+        #[command(flatten)]
+        s3: S3Args,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
+    // Initialize tracing on stderr so stdout stays parseable;
+    // GALLERY_LOG_FORMAT=json emits one JSON object per line
+    let json_logs = std::env::var("GALLERY_LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json"));
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "gallery_cli=info".into()),
+                .unwrap_or_else(|_| "gallery=info".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json().with_writer(std::io::stderr)))
+        .with((!json_logs).then(|| tracing_subscriber::fmt::layer().with_writer(std::io::stderr)))
         .init();
 
     let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
 
     match cli.command {
-        Commands::Upload { paths, name, bucket, expires_in_days } => {
-            commands::upload::execute(paths, name, bucket, expires_in_days).await?;
+        Commands::Upload(args) => {
+            commands::upload::execute((*args).with_config(&config)).await?;
+        }
+        Commands::Delete { album_id, s3 } => {
+            commands::delete::execute(album_id, s3.with_config(&config)).await?;
+        }
+        Commands::Remove { album_id, image_id, s3 } => {
+            commands::remove::execute(album_id, image_id, s3.with_config(&config)).await?;
+        }
+        Commands::Rename { album_id, new_name, s3 } => {
+            commands::rename::execute(album_id, new_name, s3.with_config(&config)).await?;
+        }
+        Commands::Caption { album_id, image_id, text, s3 } => {
+            commands::caption::execute(album_id, image_id, text, s3.with_config(&config)).await?;
+        }
+        Commands::Tag { album_id, image_id, tags, s3 } => {
+            commands::tag::execute(album_id, image_id, tags, s3.with_config(&config)).await?;
+        }
+        Commands::SetCover { album_id, image_id, s3 } => {
+            commands::set_cover::execute(album_id, image_id, s3.with_config(&config)).await?;
+        }
+        Commands::Reorder { album_id, order, s3 } => {
+            commands::reorder::execute(album_id, order, s3.with_config(&config)).await?;
+        }
+        Commands::Export { album_id, dest_dir, s3 } => {
+            commands::export::execute(album_id, dest_dir, s3.with_config(&config)).await?;
+        }
+        Commands::StaticExport { album_id, dest_dir, s3 } => {
+            commands::static_export::execute(album_id, dest_dir, s3.with_config(&config)).await?;
+        }
+        Commands::Reprocess(args) => {
+            commands::reprocess::execute(args.with_config(&config)).await?;
+        }
+        Commands::SetupLifecycle { days, s3 } => {
+            commands::setup_lifecycle::execute(days, s3.with_config(&config)).await?;
+        }
+        Commands::Extend { album_id, days, s3 } => {
+            commands::extend::execute(album_id, days, s3.with_config(&config)).await?;
+        }
+        Commands::PruneOrphans { album_id, dry_run, s3 } => {
+            commands::prune_orphans::execute(album_id, dry_run, s3.with_config(&config)).await?;
+        }
+        Commands::RefreshUrls { album_id, expires_days, out, s3 } => {
+            commands::refresh_urls::execute(album_id, expires_days, out, s3.with_config(&config)).await?;
+        }
+        Commands::SignUrl { album_id, secret_env, expires } => {
+            commands::sign_url::execute(album_id, secret_env, expires)?;
+        }
+        Commands::DuplicateReport { output, s3 } => {
+            commands::duplicate_report::execute(output, s3.with_config(&config)).await?;
+        }
+        Commands::Stats { output, s3 } => {
+            commands::stats::execute(output, s3.with_config(&config)).await?;
         }
-        Commands::Delete { album_id, bucket } => {
-            commands::delete::execute(album_id, bucket).await?;
+        Commands::Verify { album_id, s3 } => {
+            commands::verify::execute(album_id, s3.with_config(&config)).await?;
         }
     }
 
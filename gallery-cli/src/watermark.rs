@@ -0,0 +1,73 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView, RgbaImage};
+use std::path::Path;
+
+/// Watermark width as a fraction of the marked image's width, so previews and
+/// originals carry the same relative mark
+const WATERMARK_WIDTH_FRACTION: f32 = 0.25;
+
+/// Gap between the watermark and the image edge, as a fraction of the shorter side
+const WATERMARK_MARGIN_FRACTION: f32 = 0.02;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+    Center,
+}
+
+/// A PNG overlaid onto previews (and optionally originals)
+#[derive(Debug)]
+pub struct Watermark {
+    mark: RgbaImage,
+    position: WatermarkPosition,
+    /// Also burn the mark into the stored original
+    pub apply_to_original: bool,
+}
+
+impl Watermark {
+    /// Load a watermark image, folding `opacity` (0.0-1.0) into its alpha channel
+    pub fn load(path: &Path, position: WatermarkPosition, opacity: f32, apply_to_original: bool) -> Result<Self> {
+        let mut mark = image::open(path)
+            .with_context(|| format!("Failed to open watermark: {}", path.display()))?
+            .to_rgba8();
+
+        for pixel in mark.pixels_mut() {
+            pixel[3] = (f32::from(pixel[3]) * opacity).round() as u8;
+        }
+
+        Ok(Self {
+            mark,
+            position,
+            apply_to_original,
+        })
+    }
+
+    /// Return a copy of `img` with the watermark composited on top
+    pub fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        let (width, height) = img.dimensions();
+        let (mark_width, mark_height) = self.mark.dimensions();
+
+        let target_width = ((width as f32 * WATERMARK_WIDTH_FRACTION).round() as u32).max(1);
+        let target_height = ((mark_height as f32 * target_width as f32 / mark_width as f32).round() as u32).clamp(1, height);
+        let mark = imageops::resize(&self.mark, target_width, target_height, FilterType::Lanczos3);
+
+        let margin = (width.min(height) as f32 * WATERMARK_MARGIN_FRACTION).round() as i64;
+        let (free_x, free_y) = (i64::from(width) - i64::from(target_width), i64::from(height) - i64::from(target_height));
+        let (x, y) = match self.position {
+            WatermarkPosition::TopLeft => (margin, margin),
+            WatermarkPosition::TopRight => (free_x - margin, margin),
+            WatermarkPosition::BottomLeft => (margin, free_y - margin),
+            WatermarkPosition::BottomRight => (free_x - margin, free_y - margin),
+            WatermarkPosition::Center => (free_x / 2, free_y / 2),
+        };
+
+        let mut canvas = img.to_rgba8();
+        imageops::overlay(&mut canvas, &mark, x, y);
+        DynamicImage::ImageRgba8(canvas)
+    }
+}
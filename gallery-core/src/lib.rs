@@ -1,8 +1,11 @@
 pub mod manifest;
+pub mod password;
 pub mod s3;
+pub mod signing;
 
-pub use manifest::{AlbumManifest, ImageInfo};
-pub use s3::S3Client;
+pub use manifest::{AlbumManifest, ExifData, ImageInfo, PreviewVariant, Theme, Tier};
+pub use s3::{ClientOptions, ObjectStream, S3Client, UploadOptions};
 
-// Re-export DateTime for use in CLI
-pub use aws_sdk_s3::primitives::DateTime;
+// Re-export SDK types for use in CLI and web
+pub use aws_sdk_s3::primitives::{ByteStream, DateTime};
+pub use aws_sdk_s3::types::StorageClass;
@@ -1,14 +1,123 @@
+use anyhow::Context;
+use aws_sdk_s3::primitives::DateTime;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::{S3Client, UploadOptions};
+
+/// Shape of manifests written by this version; bump when adding a migration step
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+/// Manifests written before versioning carry no `schema_version` and are treated as v1
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// Albums written before `allow_download` existed keep their Download button
+fn allow_download_default() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlbumManifest {
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
     pub id: String,
+    /// Content-derived ID of the image set, stored when `id` is a custom slug
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
     pub name: String,
     pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Date of the event or shoot as "YYYY-MM-DD"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_date: Option<String>,
+    /// Image used for listings and link previews; the first image when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover_image_id: Option<String>,
+    /// Argon2 hash of the album password; open album when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_hash: Option<String>,
+    /// RFC 3339 time after which the album is no longer served; kept forever when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Seconds each image is shown during slideshow playback; the viewer default (5s) when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slideshow_interval_secs: Option<u32>,
+    /// Whether the slideshow restarts from the first image instead of stopping at the last
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slideshow_loop: Option<bool>,
+    /// Grid layout of the gallery page: "justified" (the default when unset) or "square"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Whether viewers get a Download button; `false` makes a view-only gallery
+    #[serde(default = "allow_download_default")]
+    pub allow_download: bool,
+    /// Branding of the gallery page; the stock look when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
     pub images: Vec<ImageInfo>,
 }
 
+/// Colors and font of a gallery page. Each unset field keeps the stock look
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Theme {
+    /// Hex color of focus outlines and selected tag chips
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accent_color: Option<String>,
+    /// Hex color of the page background
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
+    /// CSS font-family list for the page text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<String>,
+}
+
+impl Theme {
+    /// Whether `value` is a CSS hex color: `#` and 3, 4, 6 or 8 hex digits
+    pub fn is_valid_color(value: &str) -> bool {
+        value.strip_prefix('#').is_some_and(|digits| {
+            matches!(digits.len(), 3 | 4 | 6 | 8) && digits.bytes().all(|b| b.is_ascii_hexdigit())
+        })
+    }
+
+    /// Whether `value` is a plain font-family list (names, quotes, commas), safe to put in a style block
+    pub fn is_valid_font(value: &str) -> bool {
+        !value.trim().is_empty()
+            && value.len() <= 200
+            && value
+                .chars()
+                .all(|c| c.is_alphanumeric() || matches!(c, ' ' | ',' | '-' | '_' | '"' | '\''))
+    }
+}
+
+/// One stored rendition of an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tier {
+    Thumbnail,
+    Preview,
+    Original,
+}
+
+impl Tier {
+    pub const ALL: [Tier; 3] = [Tier::Thumbnail, Tier::Preview, Tier::Original];
+}
+
+impl std::str::FromStr for Tier {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "thumbnail" | "thumbnails" => Ok(Tier::Thumbnail),
+            "preview" | "previews" => Ok(Tier::Preview),
+            "original" | "originals" => Ok(Tier::Original),
+            _ => Err(format!("unknown tier {value:?}; expected thumbnail, preview or original")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImageInfo {
     pub id: String,
@@ -19,6 +128,37 @@ pub struct ImageInfo {
     pub thumbnail_path: String,
     pub preview_path: String,
     pub original_path: String,
+    /// AVIF renditions, present when uploaded with `--format avif`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_avif_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_avif_path: Option<String>,
+    /// Preview renditions by width for `srcset`, present when uploaded with `--responsive`.
+    /// The widest one is the regular preview at `preview_path`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preview_variants: Vec<PreviewVariant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_size_bytes: Option<u64>,
+    /// The stored original is a JPEG re-encode (HEIC source, watermark), so `file_hash` doesn't match it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub original_transcoded: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exif: Option<ExifData>,
+    /// EXIF orientation (2-8) of the stored original, which is served unrotated; unset when upright.
+    /// `width`, `height` and the other tiers are already upright
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+    /// Lowercase keywords (e.g. `portrait`, `bw`) the gallery can filter by
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Tiers whose paths are recorded but that haven't been uploaded yet (`upload --tiers`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_tiers: Vec<Tier>,
+    /// Tiny blurred placeholder as a `data:` URI, shown before the thumbnail arrives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lqip: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thumbnail_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -27,25 +167,107 @@ pub struct ImageInfo {
     pub original_url: Option<String>,
 }
 
+/// One width of a responsive preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewVariant {
+    /// Width in pixels, as used by `srcset` `w` descriptors
+    pub width: u32,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Shooting metadata read from the original's EXIF block
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExifData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iso: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub f_number: Option<f64>,
+    /// Shutter speed as displayed, e.g. "1/125"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exposure_time: Option<String>,
+    /// Focal length in millimetres
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focal_length: Option<f64>,
+    /// Capture time as "YYYY-MM-DDTHH:MM:SS" (camera local time, no offset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_taken: Option<String>,
+}
+
 impl AlbumManifest {
     pub fn new(name: String) -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             id: Uuid::new_v4().to_string(),
+            content_hash: None,
             name,
             created_at: chrono::Utc::now().to_rfc3339(),
+            description: None,
+            event_date: None,
+            cover_image_id: None,
+            password_hash: None,
+            expires_at: None,
+            slideshow_interval_secs: None,
+            slideshow_loop: None,
+            layout: None,
+            allow_download: true,
+            theme: None,
             images: Vec::new(),
         }
     }
 
+    /// Whether `id` is usable as an album ID: 1-64 lowercase letters, digits and inner hyphens.
+    /// Covers content-hash IDs, UUIDs and custom slugs alike.
+    pub fn is_valid_id(id: &str) -> bool {
+        !id.is_empty()
+            && id.len() <= 64
+            && !id.starts_with('-')
+            && !id.ends_with('-')
+            && id.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+    }
+
     pub fn with_id(name: String, id: String) -> Self {
         Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
             id,
+            content_hash: None,
             name,
             created_at: chrono::Utc::now().to_rfc3339(),
+            description: None,
+            event_date: None,
+            cover_image_id: None,
+            password_hash: None,
+            expires_at: None,
+            slideshow_interval_secs: None,
+            slideshow_loop: None,
+            layout: None,
+            allow_download: true,
+            theme: None,
             images: Vec::new(),
         }
     }
 
+    /// Whether `expires_at` has passed (an unparseable date counts as not expired)
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .is_some_and(|at| at < chrono::Utc::now())
+    }
+
+    /// `expires_at` as an S3 timestamp, for the `Expires` of the album's objects
+    pub fn expiry(&self) -> Option<DateTime> {
+        self.expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| DateTime::from_secs(at.timestamp()))
+    }
+
     pub fn add_image(&mut self, info: ImageInfo) {
         self.images.push(info);
     }
@@ -54,20 +276,165 @@ impl AlbumManifest {
         Ok(serde_json::to_string_pretty(self)?)
     }
 
+    /// Parse a manifest of any schema version, upgraded to the current shape
     pub fn from_json(json: &str) -> anyhow::Result<Self> {
-        Ok(serde_json::from_str(json)?)
+        let mut manifest: Self = serde_json::from_str(json)?;
+        manifest.migrate();
+        Ok(manifest)
+    }
+
+    /// Upgrade an older manifest in place. Newer optional fields already default
+    /// through serde; this fixes up anything that needs more than a default.
+    pub fn migrate(&mut self) {
+        if self.schema_version < 2 {
+            // v1 manifests could keep a cover pointing at an image removed since
+            if let Some(cover) = &self.cover_image_id {
+                if !self.images.iter().any(|img| &img.id == cover) {
+                    self.cover_image_id = None;
+                }
+            }
+        }
+
+        self.schema_version = CURRENT_SCHEMA_VERSION;
+    }
+
+    /// S3 key of the manifest for an album
+    pub fn key(album_id: &str) -> String {
+        format!("{album_id}/manifest.json")
+    }
+
+    /// Download and parse an album's manifest from S3
+    pub async fn fetch(s3: &S3Client, album_id: &str) -> anyhow::Result<Self> {
+        let data = s3.download_file(&Self::key(album_id)).await?;
+        let json = String::from_utf8(data).context("Manifest is not valid UTF-8")?;
+        Self::from_json(&json).context("Failed to parse manifest")
+    }
+
+    /// Serialize and upload this manifest to S3
+    pub async fn store(&self, s3: &S3Client, expires: Option<DateTime>) -> anyhow::Result<()> {
+        s3.upload_bytes(self.to_json()?.into_bytes(), &Self::key(&self.id), expires)
+            .await
+    }
+
+    /// Serialize and upload this manifest to S3 with explicit object settings
+    pub async fn store_with(&self, s3: &S3Client, options: &UploadOptions) -> anyhow::Result<()> {
+        s3.upload_bytes_with(self.to_json()?.into_bytes(), &Self::key(&self.id), options)
+            .await
+    }
+
+    /// Rewrite the stored manifest, keeping the storage class, KMS encryption and `Expires` of
+    /// the object it replaces (the album's expiry if it had none)
+    pub async fn update(&self, s3: &S3Client) -> anyhow::Result<()> {
+        let mut options = s3.upload_options(&Self::key(&self.id)).await?;
+        options.expires = options.expires.or_else(|| self.expiry());
+        self.store_with(s3, &options).await
+    }
+
+    /// Sort images chronologically by EXIF capture date.
+    /// Images without a capture date go last, ordered by filename.
+    pub fn sort_by_capture_date(&mut self) {
+        self.images.sort_by(|a, b| {
+            let date = |img: &ImageInfo| img.exif.as_ref().and_then(|e| e.date_taken.clone());
+            let (a_date, b_date) = (date(a), date(b));
+            a_date
+                .is_none()
+                .cmp(&b_date.is_none())
+                .then(a_date.cmp(&b_date))
+                .then_with(|| a.original_filename.cmp(&b.original_filename))
+        });
+    }
+
+    /// The album's cover image, falling back to the first image
+    pub fn cover_image(&self) -> Option<&ImageInfo> {
+        self.cover_image_id
+            .as_deref()
+            .and_then(|id| self.images.iter().find(|img| img.id == id))
+            .or_else(|| self.images.first())
+    }
+
+    /// Look up an image by ID for editing
+    pub fn image_mut(&mut self, image_id: &str) -> Option<&mut ImageInfo> {
+        self.images.iter_mut().find(|img| img.id == image_id)
+    }
+
+    /// Remove an image by ID, returning it if present
+    pub fn remove_image(&mut self, image_id: &str) -> Option<ImageInfo> {
+        let index = self.images.iter().position(|img| img.id == image_id)?;
+        Some(self.images.remove(index))
     }
 }
 
 impl ImageInfo {
+    /// Extension the stored original keeps: the source file's, lowercased (`jpg` if it has none)
+    pub fn original_extension(filename: &str) -> String {
+        std::path::Path::new(filename)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .filter(|ext| !ext.is_empty() && ext.bytes().all(|b| b.is_ascii_alphanumeric()))
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_else(|| "jpg".to_string())
+    }
+
+    /// Path of the responsive preview with the given longest edge
+    pub fn preview_variant_path(image_id: &str, size: u32) -> String {
+        format!("previews/{image_id}_{size}.jpg")
+    }
+
+    /// Whether this tier has been uploaded
+    pub fn has_tier(&self, tier: Tier) -> bool {
+        !self.missing_tiers.contains(&tier)
+    }
+
+    /// Path of the object to show for `tier`, relative to the album prefix. A thumbnail or preview
+    /// that hasn't been generated yet (browser uploads before `gallery reprocess`, `upload --tiers`)
+    /// falls back to the next larger tier that is stored; None when there is none.
+    pub fn display_path(&self, tier: Tier) -> Option<&str> {
+        let candidates = [
+            (Tier::Thumbnail, self.thumbnail_path.as_str()),
+            (Tier::Preview, self.preview_path.as_str()),
+            (Tier::Original, self.original_path.as_str()),
+        ];
+        candidates
+            .into_iter()
+            .skip_while(|(candidate, _)| *candidate != tier)
+            .find(|(candidate, _)| self.has_tier(*candidate))
+            .map(|(_, path)| path)
+    }
+
+    /// Every S3 object stored for this image, relative to the album prefix.
+    /// Tiers that haven't been uploaded yet are left out.
+    pub fn object_paths(&self) -> Vec<&str> {
+        let mut paths = Vec::new();
+        if self.has_tier(Tier::Original) {
+            paths.push(self.original_path.as_str());
+        }
+        if self.has_tier(Tier::Preview) {
+            paths.push(self.preview_path.as_str());
+            paths.extend(self.preview_avif_path.as_deref());
+            paths.extend(
+                self.preview_variants
+                    .iter()
+                    .map(|variant| variant.path.as_str())
+                    .filter(|path| *path != self.preview_path),
+            );
+        }
+        if self.has_tier(Tier::Thumbnail) {
+            paths.push(self.thumbnail_path.as_str());
+            paths.extend(self.thumbnail_avif_path.as_deref());
+        }
+        paths
+    }
+
     pub fn new(
         original_filename: String,
         width: u32,
         height: u32,
         file_hash: String,
+        original_size_bytes: u64,
         _album_id: &str,
         image_id: &str,
     ) -> Self {
+        let original_extension = Self::original_extension(&original_filename);
         Self {
             id: image_id.to_string(),
             original_filename,
@@ -76,7 +443,18 @@ impl ImageInfo {
             file_hash,
             thumbnail_path: format!("thumbnails/{image_id}.jpg"),
             preview_path: format!("previews/{image_id}.jpg"),
-            original_path: format!("originals/{image_id}.jpg"),
+            original_path: format!("originals/{image_id}.{original_extension}"),
+            thumbnail_avif_path: None,
+            preview_avif_path: None,
+            preview_variants: Vec::new(),
+            original_size_bytes: Some(original_size_bytes),
+            original_transcoded: false,
+            exif: None,
+            orientation: None,
+            caption: None,
+            tags: Vec::new(),
+            missing_tiers: Vec::new(),
+            lqip: None,
             thumbnail_url: None,
             preview_url: None,
             original_url: None,
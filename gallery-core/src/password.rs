@@ -0,0 +1,32 @@
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Hash an album password with Argon2 for storage in the manifest
+pub fn hash_password(password: &str) -> anyhow::Result<String> {
+    // A random v4 UUID supplies 16 bytes of salt
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|e| anyhow::anyhow!("Failed to encode salt: {e}"))?;
+
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {e}"))?;
+
+    Ok(hash.to_string())
+}
+
+/// Hex SHA-256 of a stored hash, for access cookies that stop working once the password changes
+/// without carrying the hash itself
+pub fn hash_fingerprint(hash: &str) -> String {
+    format!("{:x}", Sha256::digest(hash.as_bytes()))
+}
+
+/// Check a password attempt against a stored Argon2 hash
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash)
+        .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+        .unwrap_or(false)
+}
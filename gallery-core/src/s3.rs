@@ -1,44 +1,149 @@
 use anyhow::{Context, Result};
+use aws_config::Region;
 use aws_sdk_s3::{
-    primitives::{ByteStream, DateTime},
+    primitives::{ByteStream, DateTime, DateTimeFormat},
     presigning::PresigningConfig,
+    types::{
+        BucketLifecycleConfiguration, ExpirationStatus, LifecycleExpiration, LifecycleRule,
+        LifecycleRuleFilter, MetadataDirective, ServerSideEncryption, StorageClass,
+    },
     Client,
 };
 use std::path::Path;
 use std::time::Duration;
 
+/// Per-object settings applied when uploading
+#[derive(Clone, Debug, Default)]
+pub struct UploadOptions {
+    pub expires: Option<DateTime>,
+    /// Storage class for the object; the bucket default (STANDARD) when unset
+    pub storage_class: Option<StorageClass>,
+    /// KMS key ID or ARN; enables SSE-KMS encryption when set
+    pub sse_kms_key_id: Option<String>,
+}
+
+/// Connection settings for `S3Client::new_with_options`
+#[derive(Clone, Debug, Default)]
+pub struct ClientOptions {
+    /// Bucket region; falls back to `GALLERY_REGION`, then the default AWS chain
+    pub region: Option<String>,
+    /// Custom endpoint URL; falls back to `AWS_ENDPOINT_URL`
+    pub endpoint_url: Option<String>,
+    /// Path-style addressing; on by default for custom endpoints and off for AWS
+    pub force_path_style: Option<bool>,
+    /// Named profile from `~/.aws/config` to take credentials and settings from; the
+    /// default chain (which honours `AWS_PROFILE`) when unset
+    pub profile: Option<String>,
+    /// Separate bucket for full-size originals (`{album-id}/originals/...`); everything else,
+    /// including manifests, stays in the main bucket. One bucket for all objects when unset
+    pub originals_bucket: Option<String>,
+    /// Namespace prepended to every key (e.g. `user-a` stores albums under `user-a/{album-id}/...`),
+    /// so several users or projects can share a bucket. Albums at the bucket root when unset
+    pub key_prefix: Option<String>,
+}
+
+/// An object body being streamed from S3, with the metadata needed to serve it
+pub struct ObjectStream {
+    pub body: ByteStream,
+    /// `Content-Type` stored with the object; `None` when S3 only has its generic default
+    pub content_type: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct S3Client {
     client: Client,
     bucket: String,
+    originals_bucket: Option<String>,
+    /// `key_prefix` with a trailing slash, or empty
+    key_prefix: String,
 }
 
 impl S3Client {
     pub async fn new(bucket: String) -> Result<Self> {
+        Self::new_with_region(bucket, None).await
+    }
+
+    /// Create a client for a bucket in an explicit region.
+    /// Falls back to `GALLERY_REGION`, then the default AWS chain (`AWS_REGION`, profile, IMDS).
+    pub async fn new_with_region(bucket: String, region: Option<String>) -> Result<Self> {
+        Self::new_with_options(bucket, ClientOptions { region, ..Default::default() }).await
+    }
+
+    /// Create a client for an S3-compatible endpoint such as MinIO; `None` falls back to `AWS_ENDPOINT_URL`
+    pub async fn new_with_endpoint(bucket: String, endpoint: Option<String>) -> Result<Self> {
+        Self::new_with_options(bucket, ClientOptions { endpoint_url: endpoint, ..Default::default() }).await
+    }
+
+    /// Create a client with explicit connection settings; unset fields fall back to the environment
+    pub async fn new_with_options(bucket: String, options: ClientOptions) -> Result<Self> {
         let mut config_loader = aws_config::defaults(aws_config::BehaviorVersion::latest());
 
-        // If AWS_ENDPOINT_URL is set, use it (for MinIO/LocalStack/etc)
-        if let Ok(endpoint_url) = std::env::var("AWS_ENDPOINT_URL") {
-            config_loader = config_loader.endpoint_url(&endpoint_url);
+        if let Some(profile) = &options.profile {
+            config_loader = config_loader.profile_name(profile);
+        }
+
+        if let Some(region) = options.region.or_else(|| std::env::var("GALLERY_REGION").ok()) {
+            config_loader = config_loader.region(Region::new(region));
+        }
+
+        // Custom endpoint for MinIO/LocalStack/etc
+        let endpoint_url = options.endpoint_url.or_else(|| std::env::var("AWS_ENDPOINT_URL").ok());
+        if let Some(endpoint_url) = &endpoint_url {
+            config_loader = config_loader.endpoint_url(endpoint_url);
         }
 
         let config = config_loader.load().await;
         let mut s3_config_builder = aws_sdk_s3::config::Builder::from(&config);
 
-        // For S3-compatible services, force path-style addressing
-        if std::env::var("AWS_ENDPOINT_URL").is_ok() {
+        // S3-compatible services generally need path-style addressing
+        if options.force_path_style.unwrap_or(endpoint_url.is_some()) {
             s3_config_builder = s3_config_builder.force_path_style(true);
         }
 
         let s3_config = s3_config_builder.build();
         let client = Client::from_conf(s3_config);
 
-        Ok(Self { client, bucket })
+        let key_prefix = options
+            .key_prefix
+            .as_deref()
+            .map(|prefix| prefix.trim_matches('/'))
+            .filter(|prefix| !prefix.is_empty())
+            .map(|prefix| format!("{prefix}/"))
+            .unwrap_or_default();
+
+        Ok(Self { client, bucket, originals_bucket: options.originals_bucket, key_prefix })
+    }
+
+    /// Object key in the bucket for an album-relative key (`{album-id}/...`).
+    /// Callers only ever see album-relative keys; listings strip the prefix again.
+    fn full_key(&self, s3_key: &str) -> String {
+        format!("{}{}", self.key_prefix, s3_key)
+    }
+
+    /// Bucket holding `s3_key`: originals go to the originals bucket when one is configured
+    fn bucket_for(&self, s3_key: &str) -> &str {
+        let is_original = s3_key
+            .split_once('/')
+            .is_some_and(|(_, rest)| rest.starts_with("originals/"));
+        match &self.originals_bucket {
+            Some(originals_bucket) if is_original => originals_bucket,
+            _ => &self.bucket,
+        }
+    }
+
+    /// Every bucket objects are stored in: the main bucket, then the originals bucket if separate
+    fn buckets(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.bucket.as_str()).chain(
+            self.originals_bucket
+                .as_deref()
+                .filter(|originals_bucket| *originals_bucket != self.bucket),
+        )
     }
 
     /// Upload a file to S3
     pub async fn upload_file(&self, local_path: &Path, s3_key: &str) -> Result<()> {
-        tracing::debug!("S3 PUT: bucket={}, key={}, local_path={:?}", self.bucket, s3_key, local_path);
+        let bucket = self.bucket_for(s3_key);
+        tracing::debug!("S3 PUT: bucket={}, key={}, local_path={:?}", bucket, s3_key, local_path);
 
         let body = ByteStream::from_path(local_path)
             .await
@@ -46,8 +151,8 @@ impl S3Client {
 
         self.client
             .put_object()
-            .bucket(&self.bucket)
-            .key(s3_key)
+            .bucket(bucket)
+            .key(self.full_key(s3_key))
             .body(body)
             .content_type(Self::guess_content_type(s3_key))
             .send()
@@ -60,21 +165,41 @@ impl S3Client {
 
     /// Upload bytes to S3
     pub async fn upload_bytes(&self, data: Vec<u8>, s3_key: &str, expires: Option<DateTime>) -> Result<()> {
-        tracing::debug!("S3 PUT (bytes): bucket={}, key={}, size={} bytes", self.bucket, s3_key, data.len());
+        let options = UploadOptions {
+            expires,
+            ..Default::default()
+        };
+        self.upload_bytes_with(data, s3_key, &options).await
+    }
+
+    /// Upload bytes to S3 with explicit object settings
+    pub async fn upload_bytes_with(&self, data: Vec<u8>, s3_key: &str, options: &UploadOptions) -> Result<()> {
+        let bucket = self.bucket_for(s3_key);
+        tracing::debug!("S3 PUT (bytes): bucket={}, key={}, size={} bytes", bucket, s3_key, data.len());
 
         let body = ByteStream::from(data);
 
         let mut request = self.client
             .put_object()
-            .bucket(&self.bucket)
-            .key(s3_key)
+            .bucket(bucket)
+            .key(self.full_key(s3_key))
             .body(body)
             .content_type(Self::guess_content_type(s3_key));
 
-        if let Some(expires_at) = expires {
+        if let Some(expires_at) = options.expires {
             request = request.expires(expires_at);
         }
 
+        if let Some(storage_class) = &options.storage_class {
+            request = request.storage_class(storage_class.clone());
+        }
+
+        if let Some(kms_key_id) = &options.sse_kms_key_id {
+            request = request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .ssekms_key_id(kms_key_id);
+        }
+
         request
             .send()
             .await
@@ -86,12 +211,13 @@ impl S3Client {
 
     /// Download a file from S3
     pub async fn download_file(&self, s3_key: &str) -> Result<Vec<u8>> {
-        tracing::debug!("S3 GET: bucket={}, key={}", self.bucket, s3_key);
+        let bucket = self.bucket_for(s3_key);
+        tracing::debug!("S3 GET: bucket={}, key={}", bucket, s3_key);
 
         let response = self.client
             .get_object()
-            .bucket(&self.bucket)
-            .key(s3_key)
+            .bucket(bucket)
+            .key(self.full_key(s3_key))
             .send()
             .await
             .context("Failed to download from S3")?;
@@ -107,28 +233,70 @@ impl S3Client {
         Ok(bytes)
     }
 
-    /// Delete all objects with a prefix (album deletion)
-    pub async fn delete_prefix(&self, prefix: &str) -> Result<()> {
-        // List all objects with the prefix
-        let objects = self.client
-            .list_objects_v2()
-            .bucket(&self.bucket)
-            .prefix(prefix)
+    /// Open an object for streaming, without buffering the body in memory
+    pub async fn open_stream(&self, s3_key: &str) -> Result<ObjectStream> {
+        let bucket = self.bucket_for(s3_key);
+        tracing::debug!("S3 GET (stream): bucket={}, key={}", bucket, s3_key);
+
+        let response = self.client
+            .get_object()
+            .bucket(bucket)
+            .key(self.full_key(s3_key))
+            .send()
+            .await
+            .context("Failed to download from S3")?;
+
+        // Objects uploaded without a type report one of these; they say nothing about the content
+        let content_type = response
+            .content_type
+            .filter(|ct| !ct.is_empty() && ct != "binary/octet-stream" && ct != "application/octet-stream");
+
+        Ok(ObjectStream {
+            body: response.body,
+            content_type,
+        })
+    }
+
+    /// Delete a single object
+    pub async fn delete_object(&self, s3_key: &str) -> Result<()> {
+        let bucket = self.bucket_for(s3_key);
+        tracing::debug!("S3 DELETE: bucket={}, key={}", bucket, s3_key);
+
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(self.full_key(s3_key))
             .send()
             .await
-            .context("Failed to list objects")?;
-
-        // Delete each object
-        if let Some(contents) = objects.contents {
-            for object in contents {
-                if let Some(key) = object.key {
-                    self.client
-                        .delete_object()
-                        .bucket(&self.bucket)
-                        .key(&key)
-                        .send()
-                        .await
-                        .context(format!("Failed to delete {key}"))?;
+            .context(format!("Failed to delete {s3_key}"))?;
+
+        Ok(())
+    }
+
+    /// Delete all objects with a prefix (album deletion), in every bucket
+    pub async fn delete_prefix(&self, prefix: &str) -> Result<()> {
+        for bucket in self.buckets() {
+            // List all objects with the prefix
+            let objects = self.client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(self.full_key(prefix))
+                .send()
+                .await
+                .context("Failed to list objects")?;
+
+            // Delete each object
+            if let Some(contents) = objects.contents {
+                for object in contents {
+                    if let Some(key) = object.key {
+                        self.client
+                            .delete_object()
+                            .bucket(bucket)
+                            .key(&key)
+                            .send()
+                            .await
+                            .context(format!("Failed to delete {key}"))?;
+                    }
                 }
             }
         }
@@ -136,23 +304,99 @@ impl S3Client {
         Ok(())
     }
 
+    /// List album IDs: the top-level `{album-id}/` prefixes in the bucket (under the key prefix)
+    pub async fn list_albums(&self) -> Result<Vec<String>> {
+        tracing::debug!("S3 LIST albums: bucket={}, prefix={}", self.bucket, self.key_prefix);
+
+        let mut album_ids = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let response = self.client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(&self.key_prefix)
+                .delimiter("/")
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .context("Failed to list albums")?;
+
+            album_ids.extend(
+                response
+                    .common_prefixes()
+                    .iter()
+                    .filter_map(|prefix| prefix.prefix()?.strip_prefix(&self.key_prefix))
+                    .map(|prefix| prefix.trim_end_matches('/').to_string()),
+            );
+
+            match response.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(album_ids)
+    }
+
+    /// List every object under `prefix` (everything for "") as (key, size in bytes),
+    /// across the main bucket and the originals bucket if there is one
+    pub async fn list_objects(&self, prefix: &str) -> Result<Vec<(String, u64)>> {
+        let mut objects = Vec::new();
+        for bucket in self.buckets() {
+            objects.extend(self.list_bucket_objects(bucket, prefix).await?);
+        }
+        Ok(objects)
+    }
+
+    async fn list_bucket_objects(&self, bucket: &str, prefix: &str) -> Result<Vec<(String, u64)>> {
+        tracing::debug!("S3 LIST: bucket={}, prefix={}", bucket, prefix);
+
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+
+        loop {
+            let response = self.client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(self.full_key(prefix))
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .context("Failed to list objects")?;
+
+            objects.extend(response.contents().iter().filter_map(|object| {
+                let size = object.size().and_then(|size| u64::try_from(size).ok()).unwrap_or(0);
+                Some((object.key()?.strip_prefix(&self.key_prefix)?.to_string(), size))
+            }));
+
+            match response.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+
+        Ok(objects)
+    }
+
     /// Get public URL for an object (if bucket is public)
     pub fn get_public_url(&self, s3_key: &str) -> String {
         format!(
             "https://{}.s3.amazonaws.com/{}",
-            self.bucket, s3_key
+            self.bucket_for(s3_key), self.full_key(s3_key)
         )
     }
 
     /// Generate a presigned URL for an object (valid for specified duration)
     pub async fn generate_presigned_url(&self, s3_key: &str, expires_in: Duration) -> Result<String> {
+        // S3 accepts presigned URLs valid for up to 7 days
         let presigning_config = PresigningConfig::expires_in(expires_in)
-            .context("Failed to create presigning config")?;
+            .with_context(|| format!("Invalid presigned URL duration: {expires_in:?} (must be at most 7 days)"))?;
 
         let presigned_request = self.client
             .get_object()
-            .bucket(&self.bucket)
-            .key(s3_key)
+            .bucket(self.bucket_for(s3_key))
+            .key(self.full_key(s3_key))
             .presigned(presigning_config)
             .await
             .context("Failed to generate presigned URL")?;
@@ -160,12 +404,133 @@ impl S3Client {
         Ok(presigned_request.uri().to_string())
     }
 
+    /// Generate a presigned PUT URL so a browser can upload an object directly.
+    /// The uploader must send the same `Content-Type` header that was signed.
+    pub async fn generate_presigned_put_url(
+        &self,
+        s3_key: &str,
+        expires_in: Duration,
+        content_type: &str,
+    ) -> Result<String> {
+        let presigning_config = PresigningConfig::expires_in(expires_in)
+            .with_context(|| format!("Invalid presigned URL duration: {expires_in:?} (must be at most 7 days)"))?;
+
+        let presigned_request = self.client
+            .put_object()
+            .bucket(self.bucket_for(s3_key))
+            .key(self.full_key(s3_key))
+            .content_type(content_type)
+            .presigned(presigning_config)
+            .await
+            .context("Failed to generate presigned PUT URL")?;
+
+        Ok(presigned_request.uri().to_string())
+    }
+
+    /// Install a bucket lifecycle rule expiring every album object `days` after upload.
+    /// Albums live at the bucket root (`{album-id}/...`) or under the key prefix, so the rule covers
+    /// the whole bucket or just that prefix (and the originals bucket, if separate).
+    /// This replaces any lifecycle configuration already on the bucket.
+    pub async fn put_expiration_lifecycle(&self, days: i32) -> Result<()> {
+        let rule = LifecycleRule::builder()
+            .id("gallery-album-expiration")
+            .filter(LifecycleRuleFilter::builder().prefix(&self.key_prefix).build())
+            .expiration(LifecycleExpiration::builder().days(days).build())
+            .status(ExpirationStatus::Enabled)
+            .build()
+            .context("Failed to build lifecycle rule")?;
+
+        let configuration = BucketLifecycleConfiguration::builder()
+            .rules(rule)
+            .build()
+            .context("Failed to build lifecycle configuration")?;
+
+        for bucket in self.buckets() {
+            tracing::debug!("S3 PUT lifecycle: bucket={}, days={}", bucket, days);
+
+            self.client
+                .put_bucket_lifecycle_configuration()
+                .bucket(bucket)
+                .lifecycle_configuration(configuration.clone())
+                .send()
+                .await
+                .with_context(|| format!("Failed to set lifecycle configuration on bucket {bucket}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Storage class, KMS key and `Expires` of an object (`None` for the bucket defaults and
+    /// no expiry), to carry over when rewriting it
+    pub async fn upload_options(&self, s3_key: &str) -> Result<UploadOptions> {
+        let head = self.client
+            .head_object()
+            .bucket(self.bucket_for(s3_key))
+            .key(self.full_key(s3_key))
+            .send()
+            .await
+            .with_context(|| format!("Failed to read metadata of {s3_key}"))?;
+
+        // An unparseable `Expires` is treated as missing, as S3 itself does
+        let expires = head
+            .expires_string
+            .as_deref()
+            .and_then(|at| DateTime::from_str(at, DateTimeFormat::HttpDate).ok());
+
+        Ok(UploadOptions {
+            expires,
+            storage_class: head.storage_class,
+            sse_kms_key_id: head.ssekms_key_id,
+        })
+    }
+
+    /// Replace an object's `Expires` metadata (`None` removes it) by copying the object onto
+    /// itself; its content type, storage class and KMS encryption are kept
+    pub async fn set_expires(&self, s3_key: &str, expires: Option<DateTime>) -> Result<()> {
+        let bucket = self.bucket_for(s3_key);
+        let key = self.full_key(s3_key);
+        tracing::debug!("S3 COPY (expires): bucket={}, key={}", bucket, key);
+
+        let options = self.upload_options(s3_key).await?;
+        // The copy source is a URL path, so anything beyond unreserved characters is escaped
+        let source: String = format!("{bucket}/{key}")
+            .bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => (b as char).to_string(),
+                _ => format!("%{b:02X}"),
+            })
+            .collect();
+
+        let mut request = self.client
+            .copy_object()
+            .bucket(bucket)
+            .key(&key)
+            .copy_source(source)
+            .metadata_directive(MetadataDirective::Replace)
+            .content_type(Self::guess_content_type(s3_key))
+            .set_expires(expires)
+            .set_storage_class(options.storage_class);
+
+        if let Some(kms_key_id) = options.sse_kms_key_id {
+            request = request
+                .server_side_encryption(ServerSideEncryption::AwsKms)
+                .ssekms_key_id(kms_key_id);
+        }
+
+        request
+            .send()
+            .await
+            .with_context(|| format!("Failed to update expiry of {s3_key}"))?;
+
+        Ok(())
+    }
+
     /// Check if object exists
     pub async fn object_exists(&self, s3_key: &str) -> Result<bool> {
         match self.client
             .head_object()
-            .bucket(&self.bucket)
-            .key(s3_key)
+            .bucket(self.bucket_for(s3_key))
+            .key(self.full_key(s3_key))
             .send()
             .await
         {
@@ -174,11 +539,24 @@ impl S3Client {
         }
     }
 
+    /// Check that every bucket exists and is accessible with current credentials
+    pub async fn bucket_reachable(&self) -> bool {
+        for bucket in self.buckets() {
+            if let Err(e) = self.client.head_bucket().bucket(bucket).send().await {
+                tracing::warn!("S3 bucket {} unreachable: {:?}", bucket, e);
+                return false;
+            }
+        }
+        true
+    }
+
     fn guess_content_type(key: &str) -> &'static str {
         if key.ends_with(".jpg") || key.ends_with(".jpeg") {
             "image/jpeg"
         } else if key.ends_with(".png") {
             "image/png"
+        } else if key.ends_with(".avif") {
+            "image/avif"
         } else if key.ends_with(".json") {
             "application/json"
         } else {
@@ -0,0 +1,26 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn link_mac(key: &[u8], album_id: &str, expires_at: i64) -> HmacSha256 {
+    // HMAC accepts keys of any length, so this can't fail
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(format!("{album_id}:{expires_at}").as_bytes());
+    mac
+}
+
+/// Token for a gallery link to `album_id` that stays valid until `expires_at` (Unix seconds):
+/// the URL-safe base64 HMAC-SHA256 of `{album_id}:{expires_at}`
+pub fn sign_gallery_link(key: &[u8], album_id: &str, expires_at: i64) -> String {
+    URL_SAFE_NO_PAD.encode(link_mac(key, album_id, expires_at).finalize().into_bytes())
+}
+
+/// Whether `token` was issued with `key` for this album and expiry, compared in constant time.
+/// The expiry itself isn't checked against the clock.
+pub fn verify_gallery_link(key: &[u8], album_id: &str, expires_at: i64, token: &str) -> bool {
+    URL_SAFE_NO_PAD
+        .decode(token)
+        .is_ok_and(|signature| link_mac(key, album_id, expires_at).verify_slice(&signature).is_ok())
+}
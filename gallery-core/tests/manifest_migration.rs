@@ -0,0 +1,42 @@
+//! Manifests stored by older versions must keep loading, upgraded to the current schema
+
+use gallery_core::manifest::CURRENT_SCHEMA_VERSION;
+use gallery_core::AlbumManifest;
+
+/// Written before `schema_version` existed; its cover points at an image removed since
+const V1_MANIFEST: &str = include_str!("fixtures/manifest_v1.json");
+
+#[test]
+fn v1_manifest_migrates_to_current_schema() {
+    let manifest = AlbumManifest::from_json(V1_MANIFEST).expect("v1 manifest should parse");
+
+    assert_eq!(manifest.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(manifest.cover_image_id, None, "stale cover should be cleared");
+
+    // Everything else comes through as stored, with defaults for newer fields
+    assert_eq!(manifest.name, "Summer 2023");
+    assert_eq!(manifest.description.as_deref(), Some("Lake house weekend"));
+    assert_eq!(manifest.images.len(), 1);
+    let image = &manifest.images[0];
+    assert_eq!(image.id, "0b4fd2c8-3f7e-4d1a-9c55-6e2a7b8d9f01");
+    assert_eq!(image.exif.as_ref().and_then(|exif| exif.iso), Some(160));
+    assert!(image.missing_tiers.is_empty());
+    assert!(manifest.allow_download);
+
+    // Stored again, it is a current manifest that needs no further migration
+    let json = manifest.to_json().unwrap();
+    assert!(json.contains(&format!("\"schema_version\": {CURRENT_SCHEMA_VERSION}")));
+    assert_eq!(AlbumManifest::from_json(&json).unwrap().cover_image_id, None);
+}
+
+#[test]
+fn v1_manifest_keeps_a_cover_that_still_exists() {
+    let json = V1_MANIFEST.replace(
+        "5d1a8b2e-6c3f-4e7a-9b0d-2f4c6e8a1b3d",
+        "0b4fd2c8-3f7e-4d1a-9c55-6e2a7b8d9f01",
+    );
+    let manifest = AlbumManifest::from_json(&json).unwrap();
+
+    assert_eq!(manifest.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(manifest.cover_image_id.as_deref(), Some("0b4fd2c8-3f7e-4d1a-9c55-6e2a7b8d9f01"));
+}
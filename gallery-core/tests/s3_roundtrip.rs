@@ -0,0 +1,92 @@
+//! End-to-end checks of `S3Client` against a real S3 API (MinIO, LocalStack, ...).
+//!
+//! Skipped unless `GALLERY_TEST_ENDPOINT` is set. The bucket (`GALLERY_TEST_BUCKET`, default
+//! `gallery-test`) must already exist; credentials come from the usual `AWS_*` variables:
+//!
+//! ```bash
+//! GALLERY_TEST_ENDPOINT=http://localhost:9000 AWS_ACCESS_KEY_ID=minioadmin \
+//!     AWS_SECRET_ACCESS_KEY=minioadmin AWS_REGION=us-east-1 cargo test -p gallery-core --test s3_roundtrip
+//! ```
+
+use anyhow::Result;
+use gallery_core::{AlbumManifest, ClientOptions, ImageInfo, S3Client};
+use std::collections::HashMap;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Client for the configured test endpoint, or `None` when the suite should be skipped
+async fn test_client() -> Result<Option<S3Client>> {
+    let Ok(endpoint_url) = std::env::var("GALLERY_TEST_ENDPOINT") else {
+        eprintln!("GALLERY_TEST_ENDPOINT is not set; skipping S3 integration test");
+        return Ok(None);
+    };
+    let bucket = std::env::var("GALLERY_TEST_BUCKET").unwrap_or_else(|_| "gallery-test".to_string());
+
+    let options = ClientOptions { endpoint_url: Some(endpoint_url), ..Default::default() };
+    Ok(Some(S3Client::new_with_options(bucket, options).await?))
+}
+
+#[tokio::test]
+async fn upload_fetch_delete_roundtrip() -> Result<()> {
+    let Some(s3) = test_client().await? else {
+        return Ok(());
+    };
+    assert!(s3.bucket_reachable().await, "test bucket is not reachable");
+
+    // A fresh album per run, so concurrent or aborted runs never see each other's objects
+    let album_id = Uuid::new_v4().to_string();
+    let image_id = Uuid::new_v4().to_string();
+    let original = b"\xFF\xD8\xFF\xE0 not really a jpeg \xFF\xD9".to_vec();
+    let preview = b"preview bytes".to_vec();
+
+    // Upload
+    let mut manifest = AlbumManifest::with_id("Integration test".to_string(), album_id.clone());
+    let image = ImageInfo::new(
+        "IMG_0001.jpg".to_string(),
+        6000,
+        4000,
+        "0".repeat(64),
+        original.len() as u64,
+        &album_id,
+        &image_id,
+    );
+    let original_key = format!("{album_id}/{}", image.original_path);
+    let preview_key = format!("{album_id}/{}", image.preview_path);
+    s3.upload_bytes(original.clone(), &original_key, None).await?;
+    s3.upload_bytes(preview.clone(), &preview_key, None).await?;
+    manifest.add_image(image);
+    manifest.store(&s3, None).await?;
+
+    // Manifest fetch
+    let fetched = AlbumManifest::fetch(&s3, &album_id).await?;
+    assert_eq!(fetched.id, album_id);
+    assert_eq!(fetched.name, "Integration test");
+    assert_eq!(fetched.images.len(), 1);
+    assert_eq!(fetched.images[0].id, image_id);
+    assert!(s3.list_albums().await?.contains(&album_id));
+
+    // Image fetch, buffered and streamed
+    assert_eq!(s3.download_file(&original_key).await?, original);
+    let streamed = s3.open_stream(&preview_key).await?.body.collect().await?.to_vec();
+    assert_eq!(streamed, preview);
+    assert!(s3.object_exists(&original_key).await?);
+    let url = s3.generate_presigned_url(&original_key, Duration::from_secs(60)).await?;
+    assert!(url.contains(&album_id));
+
+    let listed: HashMap<String, u64> = s3.list_objects(&format!("{album_id}/")).await?.into_iter().collect();
+    assert_eq!(listed.len(), 3, "unexpected objects: {listed:?}");
+    assert!(listed.contains_key(&AlbumManifest::key(&album_id)));
+    assert_eq!(listed.get(&original_key), Some(&(original.len() as u64)));
+    assert_eq!(listed.get(&preview_key), Some(&(preview.len() as u64)));
+
+    // Delete
+    s3.delete_object(&preview_key).await?;
+    assert!(!s3.object_exists(&preview_key).await?);
+    s3.delete_prefix(&format!("{album_id}/")).await?;
+    assert!(s3.list_objects(&format!("{album_id}/")).await?.is_empty());
+    assert!(!s3.object_exists(&original_key).await?);
+    assert!(AlbumManifest::fetch(&s3, &album_id).await.is_err());
+    assert!(!s3.list_albums().await?.contains(&album_id));
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+//! Checks of the object settings `S3Client` sends, against an in-memory stand-in for S3.
+//!
+//! The stub answers just enough of the S3 REST API (GET, HEAD and PUT of single objects) for
+//! manifest rewrites, echoing back the metadata headers each object was stored with the way
+//! S3 does, and records every request so tests can assert on the headers that went out.
+//! Nothing here needs network access or real credentials.
+
+use anyhow::Result;
+use gallery_core::{AlbumManifest, ClientOptions, S3Client, StorageClass, UploadOptions};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Object metadata S3 returns on GET and HEAD of an object
+const ECHOED_HEADERS: &[&str] = &[
+    "content-type",
+    "expires",
+    "x-amz-storage-class",
+    "x-amz-server-side-encryption",
+    "x-amz-server-side-encryption-aws-kms-key-id",
+];
+
+/// A request as the stub received it, with lowercased header names
+#[derive(Clone, Debug)]
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+}
+
+/// Metadata headers and body of a stored object
+type Object = (Vec<(String, String)>, Vec<u8>);
+
+#[derive(Default)]
+struct Objects {
+    /// Object path to the object stored there
+    stored: HashMap<String, Object>,
+    requests: Vec<Request>,
+}
+
+struct StubS3 {
+    endpoint_url: String,
+    objects: Arc<Mutex<Objects>>,
+}
+
+impl StubS3 {
+    async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint_url = format!("http://{}", listener.local_addr()?);
+        let objects = Arc::new(Mutex::new(Objects::default()));
+
+        let shared = Arc::clone(&objects);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, Arc::clone(&shared)));
+            }
+        });
+
+        Ok(Self { endpoint_url, objects })
+    }
+
+    /// Client for the stub with throwaway static credentials
+    async fn client(&self) -> Result<S3Client> {
+        std::env::set_var("AWS_ACCESS_KEY_ID", "stub-access-key");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "stub-secret-key");
+        let options = ClientOptions {
+            region: Some("us-east-1".to_string()),
+            endpoint_url: Some(self.endpoint_url.clone()),
+            ..Default::default()
+        };
+        S3Client::new_with_options("bucket".to_string(), options).await
+    }
+
+    /// Every PUT of `path` so far, oldest first
+    fn puts(&self, path: &str) -> Vec<Request> {
+        let objects = self.objects.lock().unwrap();
+        objects
+            .requests
+            .iter()
+            .filter(|request| request.method == "PUT" && request.path.split('?').next() == Some(path))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Answer requests on one connection until the client closes it
+async fn serve(stream: TcpStream, objects: Arc<Mutex<Objects>>) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if stream.read_line(&mut request_line).await? == 0 {
+            return Ok(());
+        }
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let length = headers.get("content-length").and_then(|l| l.parse().ok()).unwrap_or(0);
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).await?;
+        if headers.get("content-encoding").is_some_and(|e| e.contains("aws-chunked")) {
+            body = decode_aws_chunked(&body);
+        }
+
+        let object_path = path.split('?').next().unwrap_or_default().to_string();
+        let response = {
+            let mut objects = objects.lock().unwrap();
+            objects.requests.push(Request { method: method.clone(), path: path.clone(), headers: headers.clone() });
+            match method.as_str() {
+                "PUT" => {
+                    let metadata = ECHOED_HEADERS
+                        .iter()
+                        .filter_map(|name| headers.get(*name).map(|value| (name.to_string(), value.clone())))
+                        .collect();
+                    objects.stored.insert(object_path, (metadata, body));
+                    response(200, &[("etag".to_string(), "\"stub\"".to_string())], b"", false)
+                }
+                "GET" | "HEAD" => match objects.stored.get(&object_path) {
+                    Some((metadata, body)) => response(200, metadata, body, method == "HEAD"),
+                    None => {
+                        let content_type = ("content-type".to_string(), "application/xml".to_string());
+                        response(404, &[content_type], NO_SUCH_KEY, method == "HEAD")
+                    }
+                },
+                _ => response(501, &[], b"", false),
+            }
+        };
+        stream.get_mut().write_all(&response).await?;
+    }
+}
+
+const NO_SUCH_KEY: &[u8] = b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+<Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message></Error>";
+
+fn response(status: u16, headers: &[(String, String)], body: &[u8], head: bool) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {status} Stub\r\ncontent-length: {}\r\n", body.len());
+    for (name, value) in headers {
+        response.push_str(&format!("{name}: {value}\r\n"));
+    }
+    response.push_str("\r\n");
+    let mut response = response.into_bytes();
+    if !head {
+        response.extend_from_slice(body);
+    }
+    response
+}
+
+/// Payload of an `aws-chunked` body: `{hex size}[;ext]\r\n{data}\r\n` chunks up to a zero-size
+/// one, followed by trailers
+fn decode_aws_chunked(mut encoded: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::new();
+    while let Some(line_end) = encoded.windows(2).position(|w| w == b"\r\n") {
+        let size_field = String::from_utf8_lossy(&encoded[..line_end]);
+        let size = usize::from_str_radix(size_field.split(';').next().unwrap_or_default().trim(), 16).unwrap_or(0);
+        if size == 0 {
+            break;
+        }
+        let start = line_end + 2;
+        decoded.extend_from_slice(&encoded[start..start + size]);
+        encoded = &encoded[start + size + 2..];
+    }
+    decoded
+}
+
+#[tokio::test]
+async fn manifest_rewrite_keeps_storage_class_and_encryption() -> Result<()> {
+    let stub = StubS3::start().await?;
+    let s3 = stub.client().await?;
+
+    // Stored the way `upload --storage-class ... --sse-kms-key-id ...` writes it
+    let album_id = "4f6a0d3e-2c1b-4e8a-9d7f-1a2b3c4d5e6f";
+    let manifest = AlbumManifest::with_id("Stub test".to_string(), album_id.to_string());
+    let options = UploadOptions {
+        expires: None,
+        storage_class: Some(StorageClass::StandardIa),
+        sse_kms_key_id: Some("alias/gallery".to_string()),
+    };
+    manifest.store_with(&s3, &options).await?;
+
+    // An edit command's rewrite
+    let mut fetched = AlbumManifest::fetch(&s3, album_id).await?;
+    assert_eq!(fetched.name, "Stub test");
+    fetched.name = "Renamed".to_string();
+    fetched.update(&s3).await?;
+
+    let key_path = format!("/bucket/{}", AlbumManifest::key(album_id));
+    let puts = stub.puts(&key_path);
+    assert_eq!(puts.len(), 2, "expected the initial store and one rewrite");
+    let rewrite = &puts[1].headers;
+    assert_eq!(rewrite.get("x-amz-server-side-encryption").map(String::as_str), Some("aws:kms"));
+    assert_eq!(
+        rewrite.get("x-amz-server-side-encryption-aws-kms-key-id").map(String::as_str),
+        Some("alias/gallery")
+    );
+    assert_eq!(rewrite.get("x-amz-storage-class").map(String::as_str), Some("STANDARD_IA"));
+
+    assert_eq!(AlbumManifest::fetch(&s3, album_id).await?.name, "Renamed");
+
+    // Plain uploads stay unencrypted in the bucket's default class
+    manifest.store(&s3, None).await?;
+    let plain = &stub.puts(&key_path)[2].headers;
+    assert!(!plain.contains_key("x-amz-server-side-encryption"));
+    assert!(!plain.contains_key("x-amz-storage-class"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn manifest_rewrite_keeps_expires() -> Result<()> {
+    let stub = StubS3::start().await?;
+    let s3 = stub.client().await?;
+
+    // Stored the way `upload --expires-in-days` writes it
+    let album_id = "9b8c7d6e-5f4a-4b3c-8d2e-1f0a9b8c7d6e";
+    let expires_at = chrono::DateTime::from_timestamp(2_000_000_000, 0).unwrap();
+    let mut manifest = AlbumManifest::with_id("Expiring".to_string(), album_id.to_string());
+    manifest.expires_at = Some(expires_at.to_rfc3339());
+    manifest.store(&s3, manifest.expiry()).await?;
+
+    let key_path = format!("/bucket/{}", AlbumManifest::key(album_id));
+    let stored_expires = stub.puts(&key_path)[0].headers.get("expires").cloned();
+    assert_eq!(stored_expires.as_deref(), Some("Wed, 18 May 2033 03:33:20 GMT"));
+
+    // The edit commands' rewrite keeps it
+    let mut fetched = AlbumManifest::fetch(&s3, album_id).await?;
+    fetched.images.clear();
+    fetched.update(&s3).await?;
+    assert_eq!(stub.puts(&key_path)[1].headers.get("expires"), stored_expires.as_ref());
+
+    // Even when the manifest itself doesn't say when it expires
+    fetched.expires_at = None;
+    fetched.update(&s3).await?;
+    assert_eq!(stub.puts(&key_path)[2].headers.get("expires"), stored_expires.as_ref());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn presigned_urls_are_signed() -> Result<()> {
+    // Presigning happens locally; the stub only supplies an endpoint and static credentials
+    let stub = StubS3::start().await?;
+    let s3 = stub.client().await?;
+
+    let key = "4f6a0d3e-2c1b-4e8a-9d7f-1a2b3c4d5e6f/previews/0b4fd2c8-3f7e-4d1a-9c55-6e2a7b8d9f01.jpg";
+    let url = s3.generate_presigned_url(key, Duration::from_secs(300)).await?;
+    assert!(url.starts_with(&format!("{}/bucket/{key}?", stub.endpoint_url)), "unexpected URL: {url}");
+    assert!(url.contains("X-Amz-Signature="), "unsigned URL: {url}");
+    assert!(url.contains("X-Amz-Expires=300"));
+    assert!(url.contains("X-Amz-Credential=stub-access-key%2F"));
+
+    let put_url = s3.generate_presigned_put_url(key, Duration::from_secs(300), "image/jpeg").await?;
+    assert!(put_url.contains("X-Amz-Signature="), "unsigned URL: {put_url}");
+
+    // S3 rejects presigned URLs valid for more than 7 days
+    assert!(s3.generate_presigned_url(key, Duration::from_secs(8 * 24 * 3600)).await.is_err());
+    assert!(stub.objects.lock().unwrap().requests.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+//! Page templates of the web gallery, shared with `gallery static-export` so offline exports
+//! render the same gallery page. Enable the `axum` feature to return them from handlers
+
+use askama::Template;
+use gallery_core::{AlbumManifest, ImageInfo, Theme};
+
+/// Landing page
+#[derive(Template)]
+#[template(path = "index.html")]
+pub struct IndexTemplate;
+
+/// Public list of albums, enabled with ENABLE_ALBUM_INDEX
+#[derive(Template)]
+#[template(path = "albums.html")]
+pub struct AlbumsTemplate {
+    pub albums: Vec<AlbumCard>,
+}
+
+pub struct AlbumCard {
+    pub id: String,
+    pub name: String,
+    pub image_count: usize,
+    pub cover_src: Option<String>,
+}
+
+/// Missing or expired album page
+#[derive(Template)]
+#[template(path = "404.html")]
+pub struct NotFoundTemplate;
+
+/// Album past its `expires_at` date
+#[derive(Template)]
+#[template(path = "expired.html")]
+pub struct ExpiredTemplate;
+
+/// Gallery link without a valid signature, or past its `exp`, when GALLERY_SIGNING_KEY is set
+#[derive(Template)]
+#[template(path = "link_expired.html")]
+pub struct LinkExpiredTemplate;
+
+/// Password prompt for protected albums
+#[derive(Template)]
+#[template(path = "password.html")]
+pub struct PasswordTemplate<'a> {
+    pub album_id: &'a str,
+    pub failed: bool,
+}
+
+/// Slideshow delay between images unless the manifest sets `slideshow_interval_secs`
+const DEFAULT_SLIDESHOW_INTERVAL_SECS: u32 = 5;
+
+/// Stock gallery look, used for every theme field the manifest leaves unset
+const DEFAULT_ACCENT_COLOR: &str = "#333";
+const DEFAULT_BACKGROUND_COLOR: &str = "#ffffff";
+const DEFAULT_FONT: &str = r#"-apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif"#;
+
+/// Gallery page with thumbnail grid and lightbox
+#[derive(Template)]
+#[template(path = "gallery.html")]
+pub struct GalleryTemplate<'a> {
+    pub album_id: &'a str,
+    pub manifest: &'a AlbumManifest,
+    pub social_description: String,
+    pub og_url: Option<String>,
+    pub og_image: Option<String>,
+    pub event_date: Option<String>,
+    /// Expiry date for the "Available until" line, for albums uploaded with `--expires-in-days`
+    pub available_until: Option<String>,
+    pub thumbnails: Vec<Thumbnail<'a>>,
+    /// Every tag used in the album, for the filter chips
+    pub tags: Vec<&'a str>,
+    pub images_json: String,
+    pub slideshow_interval_ms: u64,
+    pub slideshow_loop: bool,
+    /// Uniform cropped cells instead of the justified rows (manifest `layout = "square"`)
+    pub square_layout: bool,
+    /// Show the Download button (manifest `allow_download`)
+    pub allow_download: bool,
+    /// Page colors and font from the manifest's theme, validated again since manifests can be edited by hand
+    pub accent_color: &'a str,
+    pub background_color: &'a str,
+    pub font: &'a str,
+    /// Rendered by `gallery static-export`: no server routes (icons, PWA manifest, download proxy)
+    pub static_export: bool,
+}
+
+pub struct Thumbnail<'a> {
+    pub image: &'a ImageInfo,
+    pub src: String,
+    /// Inline placeholder shown until `src` has loaded
+    pub placeholder: Option<&'a str>,
+}
+
+impl<'a> GalleryTemplate<'a> {
+    pub fn new(album_id: &'a str, manifest: &'a AlbumManifest, public_base_url: Option<&str>) -> Self {
+        let social_description = manifest
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("{} photographs", manifest.images.len()));
+
+        let og_url = public_base_url.map(|base| format!("{base}/gallery/{album_id}"));
+
+        // Crawlers need an absolute URL: prefer the presigned preview, else the proxy under the public base
+        let og_image = manifest.cover_image().and_then(|cover| {
+            cover.preview_url.clone().or_else(|| {
+                public_base_url.map(|base| format!("{base}/api/album/{album_id}/image/{}", cover.preview_path))
+            })
+        });
+
+        // Show a friendly date when it parses, otherwise the raw value
+        let event_date = manifest.event_date.as_ref().map(|date| {
+            chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(|d| d.format("%B %-d, %Y").to_string())
+                .unwrap_or_else(|_| date.clone())
+        });
+
+        let available_until = manifest.expires_at.as_deref().and_then(format_expiry);
+        let theme = manifest.theme.as_ref();
+
+        let thumbnails = manifest
+            .images
+            .iter()
+            .map(|image| Thumbnail {
+                image,
+                src: image.thumbnail_url.clone().unwrap_or_else(|| {
+                    // Fallback to proxied URL if presigned URL not available
+                    format!("/api/album/{}/image/{}", album_id, image.thumbnail_path)
+                }),
+                placeholder: image.lqip.as_deref().filter(|uri| uri.starts_with("data:image/")),
+            })
+            .collect();
+
+        let mut tags: Vec<&str> = manifest
+            .images
+            .iter()
+            .flat_map(|image| image.tags.iter().map(String::as_str))
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+
+        Self {
+            album_id,
+            manifest,
+            social_description,
+            og_url,
+            og_image,
+            event_date,
+            available_until,
+            thumbnails,
+            tags,
+            images_json: script_json(&manifest.images),
+            slideshow_interval_ms: u64::from(manifest.slideshow_interval_secs.unwrap_or(DEFAULT_SLIDESHOW_INTERVAL_SECS).max(1)) * 1000,
+            slideshow_loop: manifest.slideshow_loop.unwrap_or(false),
+            square_layout: manifest.layout.as_deref() == Some("square"),
+            allow_download: manifest.allow_download,
+            accent_color: theme
+                .and_then(|theme| theme.accent_color.as_deref())
+                .filter(|color| Theme::is_valid_color(color))
+                .unwrap_or(DEFAULT_ACCENT_COLOR),
+            background_color: theme
+                .and_then(|theme| theme.background_color.as_deref())
+                .filter(|color| Theme::is_valid_color(color))
+                .unwrap_or(DEFAULT_BACKGROUND_COLOR),
+            font: theme
+                .and_then(|theme| theme.font.as_deref())
+                .filter(|font| Theme::is_valid_font(font))
+                .unwrap_or(DEFAULT_FONT),
+            static_export: false,
+        }
+    }
+
+    /// Page for a static export: the images' URLs must already point at the exported files
+    pub fn render_static(album_id: &str, manifest: &AlbumManifest) -> askama::Result<String> {
+        let mut page = GalleryTemplate::new(album_id, manifest, None);
+        page.static_export = true;
+        // Crawlers need an absolute URL, which an export without a known host can't give
+        page.og_image = None;
+        page.render()
+    }
+}
+
+/// Day an RFC 3339 expiry timestamp falls on (in UTC), e.g. "October 21, 2026"
+fn format_expiry(expires_at: &str) -> Option<String> {
+    let expires_at = chrono::DateTime::parse_from_rfc3339(expires_at).ok()?;
+    Some(expires_at.with_timezone(&chrono::Utc).format("%B %-d, %Y").to_string())
+}
+
+/// Serialize a value for embedding inside a `<script>` block.
+/// `<`, `>` and `&` only occur inside JSON strings, where their `\u` escapes mean the same,
+/// so user text (captions, filenames) can't close the tag or open a comment early.
+fn script_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string(value)
+        .unwrap_or_else(|_| "[]".to_string())
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_json_cannot_break_out_of_the_script_block() {
+        let caption = "</script><script>alert(1)</script> <!-- & -->";
+        let json = script_json(&caption);
+
+        assert!(!json.contains(['<', '>', '&']), "unescaped markup in {json}");
+        assert_eq!(serde_json::from_str::<String>(&json).unwrap(), caption);
+    }
+}
@@ -0,0 +1,69 @@
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use futures_util::StreamExt;
+use std::time::{Duration, Instant};
+
+/// One request's outcome, logged when its response body has been fully sent (or dropped)
+struct AccessLog {
+    method: Method,
+    path: String,
+    status: StatusCode,
+    started: Instant,
+    /// Time until the handler produced the response, i.e. before any body bytes were sent
+    handler_latency: Duration,
+    bytes: u64,
+}
+
+impl Drop for AccessLog {
+    fn drop(&mut self) {
+        tracing::info!(
+            method = %self.method,
+            path = %self.path,
+            status = self.status.as_u16(),
+            bytes = self.bytes,
+            handler_ms = self.handler_latency.as_millis() as u64,
+            total_ms = self.started.elapsed().as_millis() as u64,
+            "request served"
+        );
+    }
+}
+
+/// Middleware logging method, path, status, response size and latency for every request.
+/// A large gap between `handler_ms` and `total_ms` points at a slow client rather than slow S3.
+pub async fn log(request: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let response = next.run(request).await;
+    let mut entry = AccessLog {
+        method,
+        path,
+        status: response.status(),
+        started,
+        handler_latency: started.elapsed(),
+        bytes: 0,
+    };
+
+    // Bodies of known size are logged right away so they keep their Content-Length
+    if let Some(size) = response.body().size_hint().exact() {
+        entry.bytes = size;
+        return response;
+    }
+
+    // Streamed bodies (S3 images, compressed pages) are counted as they go out
+    let (parts, body) = response.into_parts();
+    let stream = body.into_data_stream().inspect(move |chunk| {
+        // Borrow the whole entry so the closure owns it and logs when the stream is dropped
+        let entry = &mut entry;
+        if let Ok(bytes) = chunk {
+            entry.bytes += bytes.len() as u64;
+        }
+    });
+    Response::from_parts(parts, Body::from_stream(stream))
+}
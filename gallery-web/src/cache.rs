@@ -0,0 +1,37 @@
+use gallery_core::AlbumManifest;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Short-lived in-memory cache of parsed manifests, keyed by album ID
+pub struct ManifestCache {
+    entries: Mutex<HashMap<String, (Instant, Arc<AlbumManifest>)>>,
+    ttl: Duration,
+}
+
+impl ManifestCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Get a cached manifest if it hasn't expired
+    pub fn get(&self, album_id: &str) -> Option<Arc<AlbumManifest>> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(album_id)
+            .filter(|(cached_at, _)| cached_at.elapsed() < self.ttl)
+            .map(|(_, manifest)| Arc::clone(manifest))
+    }
+
+    /// Cache a manifest, dropping any expired entries
+    pub fn insert(&self, album_id: &str, manifest: AlbumManifest) -> Arc<AlbumManifest> {
+        let manifest = Arc::new(manifest);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (cached_at, _)| cached_at.elapsed() < self.ttl);
+        entries.insert(album_id.to_string(), (Instant::now(), Arc::clone(&manifest)));
+        manifest
+    }
+}
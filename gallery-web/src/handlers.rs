@@ -1,823 +1,928 @@
 use axum::{
-    extract::{Path, State},
-    http::{header, StatusCode},
-    response::{Html, IntoResponse, Response},
-    Json,
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
 };
-use gallery_core::AlbumManifest;
+use axum_extra::extract::cookie::{Cookie, SameSite, SignedCookieJar};
+use futures_util::StreamExt;
+use gallery_core::{password, signing, AlbumManifest, ImageInfo, Tier};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
 
 use crate::state::AppState;
+use gallery_templates::{
+    AlbumCard, AlbumsTemplate, ExpiredTemplate, GalleryTemplate, IndexTemplate, LinkExpiredTemplate, NotFoundTemplate,
+    PasswordTemplate,
+};
+
+#[derive(Deserialize)]
+pub struct AuthForm {
+    password: String,
+}
+
+/// Query of a signed gallery link, as printed by `gallery sign-url`
+#[derive(Deserialize)]
+pub struct LinkParams {
+    token: Option<String>,
+    /// Unix time the link stops working
+    exp: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PresignRequest {
+    filename: String,
+    #[serde(default = "default_upload_content_type")]
+    content_type: String,
+}
+
+fn default_upload_content_type() -> String {
+    "image/jpeg".to_string()
+}
+
+#[derive(Serialize)]
+pub struct PresignResponse {
+    image_id: String,
+    filename: String,
+    key: String,
+    url: String,
+    content_type: String,
+    expires_in_secs: u64,
+}
+
+/// Images uploaded through presigned URLs, to be added to the album's manifest
+#[derive(Deserialize)]
+pub struct CommitRequest {
+    /// Album name, used when the album doesn't exist yet
+    name: Option<String>,
+    images: Vec<CommitImage>,
+}
+
+#[derive(Deserialize)]
+pub struct CommitImage {
+    /// Object key returned by the presign endpoint
+    key: String,
+    filename: String,
+    width: u32,
+    height: u32,
+    /// SHA-256 of the original, hex-encoded, so re-uploads from the CLI recognise it
+    file_hash: String,
+    #[serde(default)]
+    size_bytes: u64,
+}
+
+#[derive(Serialize)]
+pub struct CommitResponse {
+    album_id: String,
+    total_images: usize,
+    /// Keys added to the manifest by this request
+    added: Vec<String>,
+    /// Keys that were skipped because no object exists there yet
+    missing: Vec<String>,
+}
+
+/// Name of the signed cookie granting access to a protected album
+fn auth_cookie_name(album_id: &str) -> String {
+    format!("gallery_auth_{album_id}")
+}
+
+/// Whether the request may view this album (always true for open albums). The cookie holds a
+/// fingerprint of the password hash it was issued for, so changing the password revokes it.
+fn is_authorized(jar: &SignedCookieJar, album_id: &str, manifest: &AlbumManifest) -> bool {
+    let Some(hash) = &manifest.password_hash else {
+        return true;
+    };
+    jar.get(&auth_cookie_name(album_id))
+        .is_some_and(|cookie| cookie.value() == password::hash_fingerprint(hash))
+}
+
+/// Name of the signed cookie remembering a valid signed link, so the password form and the
+/// page's API requests keep working without the token in their URLs
+fn link_cookie_name(album_id: &str) -> String {
+    format!("gallery_link_{album_id}")
+}
+
+/// Expiry of a signed link, if its token is valid for this album and the time hasn't passed
+fn valid_link_expiry(key: &[u8], album_id: &str, token: Option<&str>, exp: Option<&str>) -> Option<i64> {
+    let (token, exp) = (token?, exp?.parse::<i64>().ok()?);
+    (exp > chrono::Utc::now().timestamp() && signing::verify_gallery_link(key, album_id, exp, token)).then_some(exp)
+}
+
+/// Whether the request may reach this album at all: always without GALLERY_SIGNING_KEY, otherwise
+/// only with a valid signed link in the query or the cookie left by opening one
+fn has_link_access(
+    state: &AppState,
+    jar: &SignedCookieJar,
+    album_id: &str,
+    token: Option<&str>,
+    exp: Option<&str>,
+) -> bool {
+    let Some(key) = &state.signing_key else {
+        return true;
+    };
+
+    valid_link_expiry(key, album_id, token, exp).is_some()
+        || jar
+            .get(&link_cookie_name(album_id))
+            .and_then(|cookie| cookie.value().parse::<i64>().ok())
+            .is_some_and(|exp| exp > chrono::Utc::now().timestamp())
+}
+
+/// Album IDs are content hashes, UUIDs or custom slugs (see `AlbumManifest::is_valid_id`);
+/// anything else is rejected before it reaches S3 or the logs
+fn validate_album_id(album_id: &str) -> Result<(), StatusCode> {
+    if AlbumManifest::is_valid_id(album_id) {
+        Ok(())
+    } else {
+        Err(StatusCode::BAD_REQUEST)
+    }
+}
+
+/// Reject empty, `.` and `..` segments so a key can't escape the album prefix. Keys the CLI
+/// writes never contain `%`, so still-encoded input (`%2e%2e`) is refused rather than guessed at
+fn is_safe_object_path(path: &str) -> bool {
+    !path.contains(['\\', '%']) && path.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+}
 
 /// Index page
-pub async fn index() -> Html<&'static str> {
-    Html(
-        r#"
-<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Film Gallery</title>
-    <style>
-        body {
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            max-width: 800px;
-            margin: 100px auto;
-            padding: 20px;
-            text-align: center;
+pub async fn index() -> IndexTemplate {
+    IndexTemplate
+}
+
+/// Public album index; 404 unless ENABLE_ALBUM_INDEX is set
+pub async fn albums(State(state): State<AppState>) -> Response {
+    if !state.album_index {
+        return (StatusCode::NOT_FOUND, NotFoundTemplate).into_response();
+    }
+    tracing::info!("Album index request");
+
+    let album_ids = match state.s3.list_albums().await {
+        Ok(ids) => ids,
+        Err(e) => {
+            state.metrics.s3_error();
+            tracing::error!("Failed to list albums: {:?}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let state = &state;
+    let manifests = futures_util::future::join_all(
+        album_ids
+            .iter()
+            .filter(|id| AlbumManifest::is_valid_id(id))
+            .map(|id| async move { (id, cached_manifest(state, id).await) }),
+    )
+    .await;
+
+    // Protected and expired albums stay reachable by link but are never listed
+    let mut listed: Vec<_> = manifests
+        .into_iter()
+        .filter_map(|(id, manifest)| Some((id, manifest.ok()?)))
+        .filter(|(_, manifest)| manifest.password_hash.is_none() && !manifest.is_expired())
+        .collect();
+    listed.sort_by(|(_, a), (_, b)| b.created_at.cmp(&a.created_at));
+
+    let mut albums = Vec::with_capacity(listed.len());
+    for (album_id, manifest) in listed {
+        let cover_src = match manifest.cover_image() {
+            Some(cover) => {
+                let key = format!("{album_id}/{}", cover.thumbnail_path);
+                Some(
+                    state
+                        .presigned_url(&key)
+                        .await
+                        .unwrap_or_else(|_| format!("/api/album/{album_id}/image/{}", cover.thumbnail_path)),
+                )
+            }
+            None => None,
+        };
+
+        albums.push(AlbumCard {
+            id: album_id.clone(),
+            name: manifest.name.clone(),
+            image_count: manifest.images.len(),
+            cover_src,
+        });
+    }
+
+    AlbumsTemplate { albums }.into_response()
+}
+
+/// Load a manifest through the in-memory cache
+async fn cached_manifest(state: &AppState, album_id: &str) -> anyhow::Result<Arc<AlbumManifest>> {
+    if let Some(manifest) = state.manifest_cache.get(album_id) {
+        return Ok(manifest);
+    }
+    let manifest = AlbumManifest::fetch(&state.s3, album_id).await?;
+    Ok(state.manifest_cache.insert(album_id, manifest))
+}
+
+/// Build a `Content-Disposition: attachment` value that survives any filename.
+/// Quotes and non-ASCII are replaced in the plain `filename` and kept exact in `filename*`.
+fn attachment_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c == ' ' || (c.is_ascii_graphic() && c != '"' && c != '\\') { c } else { '_' })
+        .collect();
+
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+/// Fill in presigned thumbnail/preview/original URLs for every image
+async fn presign_image_urls(state: &AppState, album_id: &str, manifest: &mut AlbumManifest) {
+    for image in &mut manifest.images {
+        // Point missing thumbnails and previews at the larger tier shown in their place
+        if let Some(path) = image.display_path(Tier::Preview).map(str::to_string) {
+            image.preview_path = path;
         }
-        h1 {
-            font-size: 3rem;
-            font-weight: 300;
-            margin-bottom: 1rem;
+        if let Some(path) = image.display_path(Tier::Thumbnail).map(str::to_string) {
+            image.thumbnail_path = path;
         }
-        p {
-            font-size: 1.2rem;
-            color: #666;
+
+        let thumbnail_key = format!("{album_id}/{}", image.thumbnail_path);
+        let preview_key = format!("{album_id}/{}", image.preview_path);
+        let original_key = format!("{album_id}/{}", image.original_path);
+
+        image.thumbnail_url = state.presigned_url(&thumbnail_key).await.ok();
+        image.preview_url = state.presigned_url(&preview_key).await.ok();
+        for variant in &mut image.preview_variants {
+            variant.url = state.presigned_url(&format!("{album_id}/{}", variant.path)).await.ok();
+        }
+        // Originals left out with `upload --tiers` have no object to sign yet
+        if image.has_tier(Tier::Original) {
+            image.original_url = state.presigned_url(&original_key).await.ok();
         }
-    </style>
-</head>
-<body>
-    <h1>Film Gallery</h1>
-    <p>Access your private gallery using the link provided.</p>
-</body>
-</html>
-        "#,
+    }
+}
+
+/// Icons are compiled into the binary so the app needs no static file directory
+const FAVICON: &[u8] = include_bytes!("../assets/favicon.ico");
+const ICON_192: &[u8] = include_bytes!("../assets/icon-192.png");
+const ICON_512: &[u8] = include_bytes!("../assets/icon-512.png");
+
+fn icon_response(content_type: &'static str, bytes: &'static [u8]) -> Response {
+    (
+        [(header::CONTENT_TYPE, content_type), (header::CACHE_CONTROL, "public, max-age=604800")],
+        bytes,
     )
+        .into_response()
+}
+
+pub async fn favicon() -> Response {
+    icon_response("image/x-icon", FAVICON)
+}
+
+pub async fn icon_192() -> Response {
+    icon_response("image/png", ICON_192)
+}
+
+pub async fn icon_512() -> Response {
+    icon_response("image/png", ICON_512)
+}
+
+#[derive(Deserialize)]
+pub struct WebManifestParams {
+    /// Album the page was installed from, so the app opens straight into it
+    album: Option<String>,
+}
+
+/// PWA manifest letting phones add a gallery to the home screen.
+/// The album's name is only used when this request could open it anyway, so names of
+/// protected albums don't leak to anyone holding just the ID.
+pub async fn web_manifest(
+    State(state): State<AppState>,
+    Query(params): Query<WebManifestParams>,
+    jar: SignedCookieJar,
+) -> Response {
+    let album_id = params.album.filter(|id| AlbumManifest::is_valid_id(id));
+
+    let mut name = "Film Gallery".to_string();
+    if let Some(album_id) = &album_id {
+        if has_link_access(&state, &jar, album_id, None, None) {
+            if let Ok(manifest) = cached_manifest(&state, album_id).await {
+                if !manifest.is_expired() && is_authorized(&jar, album_id, &manifest) {
+                    name = manifest.name.clone();
+                }
+            }
+        }
+    }
+    let start_url = album_id.map_or_else(|| "/".to_string(), |id| format!("/gallery/{id}"));
+
+    let body = serde_json::json!({
+        "name": name,
+        "short_name": name,
+        "start_url": start_url,
+        "scope": "/",
+        "display": "standalone",
+        "background_color": "#ffffff",
+        "theme_color": "#222222",
+        "icons": [
+            { "src": "/icon-192.png", "sizes": "192x192", "type": "image/png" },
+            { "src": "/icon-512.png", "sizes": "512x512", "type": "image/png" },
+        ],
+    });
+
+    ([(header::CONTENT_TYPE, "application/manifest+json")], body.to_string()).into_response()
+}
+
+/// Liveness probe
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Prometheus metrics; needs `Authorization: Bearer $METRICS_TOKEN` when that is set
+pub async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Result<impl IntoResponse, StatusCode> {
+    if let Some(expected) = state.metrics_token.as_deref() {
+        if !has_bearer_token(&headers, expected) {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    ))
+}
+
+/// Readiness probe: verifies the S3 bucket is reachable
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, &'static str) {
+    if state.s3.bucket_reachable().await {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "s3 unreachable")
+    }
 }
 
 /// Gallery page
 pub async fn gallery(
     State(state): State<AppState>,
     Path(album_id): Path<String>,
-) -> Html<String> {
+    Query(link): Query<LinkParams>,
+    jar: SignedCookieJar,
+) -> Response {
+    if let Err(status) = validate_album_id(&album_id) {
+        return (status, NotFoundTemplate).into_response();
+    }
     tracing::info!("Gallery page request: album_id={}", album_id);
 
+    if !has_link_access(&state, &jar, &album_id, link.token.as_deref(), link.exp.as_deref()) {
+        return (StatusCode::FORBIDDEN, LinkExpiredTemplate).into_response();
+    }
+
+    // Remember a freshly opened signed link for the rest of the visit; the cookie carries its expiry
+    let link_expiry = state
+        .signing_key
+        .as_deref()
+        .and_then(|key| valid_link_expiry(key, &album_id, link.token.as_deref(), link.exp.as_deref()));
+    let jar = match link_expiry {
+        Some(exp) => jar.add(
+            Cookie::build((link_cookie_name(&album_id), exp.to_string()))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .build(),
+        ),
+        None => jar,
+    };
+
     // Verify album exists by checking manifest
-    let manifest_key = format!("{album_id}/manifest.json");
-    let manifest_data = match state.s3.download_file(&manifest_key).await {
-        Ok(data) => data,
+    let mut manifest = match cached_manifest(&state, &album_id).await {
+        Ok(m) => AlbumManifest::clone(&m),
         Err(e) => {
+            state.metrics.s3_error();
             tracing::error!("Failed to fetch manifest for album {}: {:?}", album_id, e);
-            return Html(generate_404_html());
+            return NotFoundTemplate.into_response();
         }
     };
 
-    let manifest_json = match String::from_utf8(manifest_data) {
-        Ok(json) => json,
-        Err(_) => return Html(generate_404_html()),
-    };
+    if manifest.is_expired() {
+        return (StatusCode::GONE, ExpiredTemplate).into_response();
+    }
+
+    if !is_authorized(&jar, &album_id, &manifest) {
+        return (jar, PasswordTemplate { album_id: &album_id, failed: false }).into_response();
+    }
 
-    let mut manifest: AlbumManifest = match serde_json::from_str(&manifest_json) {
+    // Generate presigned URLs for direct S3 access
+    presign_image_urls(&state, &album_id, &mut manifest).await;
+
+    (jar, GalleryTemplate::new(&album_id, &manifest, state.public_base_url.as_deref())).into_response()
+}
+
+/// Check a gallery password and set the access cookie
+pub async fn gallery_auth(
+    State(state): State<AppState>,
+    Path(album_id): Path<String>,
+    jar: SignedCookieJar,
+    Form(form): Form<AuthForm>,
+) -> Response {
+    if let Err(status) = validate_album_id(&album_id) {
+        return (status, NotFoundTemplate).into_response();
+    }
+    tracing::info!("Gallery auth attempt: album_id={}", album_id);
+
+    if !has_link_access(&state, &jar, &album_id, None, None) {
+        return (StatusCode::FORBIDDEN, LinkExpiredTemplate).into_response();
+    }
+
+    let manifest = match cached_manifest(&state, &album_id).await {
         Ok(m) => m,
-        Err(_) => return Html(generate_404_html()),
+        Err(e) => {
+            state.metrics.s3_error();
+            tracing::error!("Failed to fetch manifest for album {}: {:?}", album_id, e);
+            return NotFoundTemplate.into_response();
+        }
     };
 
-    // Generate presigned URLs for direct S3 access (valid for 7 days to match object expiration)
-    let expires_in = std::time::Duration::from_secs(7 * 24 * 3600);
-    for image in &mut manifest.images {
-        let thumbnail_key = format!("{album_id}/{}", image.thumbnail_path);
-        let preview_key = format!("{album_id}/{}", image.preview_path);
-        let original_key = format!("{album_id}/{}", image.original_path);
+    let Some(hash) = manifest.password_hash.clone() else {
+        // Open album: nothing to check
+        return Redirect::to(&format!("/gallery/{album_id}")).into_response();
+    };
 
-        image.thumbnail_url = state.s3.generate_presigned_url(&thumbnail_key, expires_in).await.ok();
-        image.preview_url = state.s3.generate_presigned_url(&preview_key, expires_in).await.ok();
-        image.original_url = state.s3.generate_presigned_url(&original_key, expires_in).await.ok();
+    // Argon2 is deliberately slow, so keep it off the async workers
+    let fingerprint = password::hash_fingerprint(&hash);
+    let verified = tokio::task::spawn_blocking(move || password::verify_password(&form.password, &hash))
+        .await
+        .unwrap_or(false);
+    if !verified {
+        tracing::warn!("Wrong password for album {}", album_id);
+        return (
+            StatusCode::UNAUTHORIZED,
+            PasswordTemplate { album_id: &album_id, failed: true },
+        )
+            .into_response();
     }
 
-    // Generate HTML
-    let html = generate_gallery_html(&album_id, &manifest);
+    // Session cookie: access lasts until the browser is closed
+    let secure = state.public_base_url.as_deref().is_some_and(|url| url.starts_with("https://"));
+    let cookie = Cookie::build((auth_cookie_name(&album_id), fingerprint))
+        .path("/")
+        .http_only(true)
+        .secure(secure)
+        .same_site(SameSite::Lax)
+        .build();
 
-    Html(html)
+    (jar.add(cookie), Redirect::to(&format!("/gallery/{album_id}"))).into_response()
+}
+
+/// Check the `Authorization: Bearer` header against the configured upload token
+fn check_upload_token(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    // Direct uploads are disabled entirely unless a token is configured
+    let expected = state.upload_token.as_deref().ok_or(StatusCode::NOT_FOUND)?;
+
+    if has_bearer_token(headers, expected) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Whether the `Authorization: Bearer` header carries `expected`
+fn has_bearer_token(headers: &HeaderMap, expected: &str) -> bool {
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // Constant time, so response timing doesn't reveal how much of a guess was right
+    provided.is_some_and(|provided| bool::from(provided.as_bytes().ct_eq(expected.as_bytes())))
+}
+
+/// Issue a presigned PUT URL for uploading an original straight to S3
+pub async fn presign_upload(
+    State(state): State<AppState>,
+    Path(album_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<PresignRequest>,
+) -> Result<Json<PresignResponse>, StatusCode> {
+    validate_album_id(&album_id)?;
+    tracing::info!("Presign upload request: album_id={}, filename={}", album_id, request.filename);
+
+    check_upload_token(&state, &headers)?;
+
+    // Originals are JPEG-only, matching the CLI
+    let extension = std::path::Path::new(&request.filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase());
+    if !matches!(extension.as_deref(), Some("jpg" | "jpeg")) || request.content_type != "image/jpeg" {
+        return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    let image_id = Uuid::new_v4().to_string();
+    let key = format!("{album_id}/originals/{image_id}.jpg");
+
+    let url = state
+        .s3
+        .generate_presigned_put_url(&key, state.presign_expiry, &request.content_type)
+        .await
+        .map_err(|e| {
+            state.metrics.s3_error();
+            tracing::error!("Failed to presign upload {}: {:?}", key, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(PresignResponse {
+        image_id,
+        filename: request.filename,
+        key,
+        url,
+        content_type: request.content_type,
+        expires_in_secs: state.presign_expiry.as_secs(),
+    }))
+}
+
+/// Add images uploaded through presigned URLs to an album's manifest, creating it if needed.
+/// Entries whose object isn't in S3 are skipped; entries already in the album are left alone.
+pub async fn commit_upload(
+    State(state): State<AppState>,
+    Path(album_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<CommitRequest>,
+) -> Result<Json<CommitResponse>, StatusCode> {
+    validate_album_id(&album_id)?;
+    tracing::info!("Commit upload request: album_id={}, images={}", album_id, request.images.len());
+
+    check_upload_token(&state, &headers)?;
+
+    // Only originals issued by the presign endpoint for this album can be committed
+    let prefix = format!("{album_id}/originals/");
+    let mut entries = Vec::with_capacity(request.images.len());
+    for image in request.images {
+        let image_id = image
+            .key
+            .strip_prefix(&prefix)
+            .and_then(|name| name.strip_suffix(".jpg"))
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or(StatusCode::BAD_REQUEST)?
+            .to_string();
+        let valid_hash = image.file_hash.len() == 64 && image.file_hash.bytes().all(|b| b.is_ascii_hexdigit());
+        if !valid_hash || image.width == 0 || image.height == 0 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        entries.push((image_id, image));
+    }
+
+    // Merge into the stored manifest rather than a possibly stale cached copy, one commit
+    // per album at a time so a concurrent one can't overwrite the images added here
+    let _album_lock = state.album_locks.lock(&album_id).await;
+    let manifest_key = AlbumManifest::key(&album_id);
+    let exists = state.s3.object_exists(&manifest_key).await.map_err(|e| {
+        state.metrics.s3_error();
+        tracing::error!("Failed to check manifest for album {}: {:?}", album_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let mut manifest = if exists {
+        AlbumManifest::fetch(&state.s3, &album_id).await.map_err(|e| {
+            state.metrics.s3_error();
+            tracing::error!("Failed to fetch manifest for album {}: {:?}", album_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+    } else {
+        let name = request.name.filter(|n| !n.trim().is_empty()).ok_or(StatusCode::BAD_REQUEST)?;
+        AlbumManifest::with_id(name, album_id.clone())
+    };
+
+    let mut added = Vec::new();
+    let mut missing = Vec::new();
+    for (image_id, image) in entries {
+        if manifest.images.iter().any(|img| img.id == image_id) {
+            continue;
+        }
+        let present = state.s3.object_exists(&image.key).await.map_err(|e| {
+            state.metrics.s3_error();
+            tracing::error!("Failed to check object {}: {:?}", image.key, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        if !present {
+            tracing::warn!("Not committing {}: object not found", image.key);
+            missing.push(image.key);
+            continue;
+        }
+
+        let mut info = ImageInfo::new(
+            image.filename,
+            image.width,
+            image.height,
+            image.file_hash.to_lowercase(),
+            image.size_bytes,
+            &album_id,
+            &image_id,
+        );
+        info.original_path = format!("originals/{image_id}.jpg");
+        // Previews and thumbnails are generated later by `gallery reprocess`
+        info.missing_tiers = vec![Tier::Thumbnail, Tier::Preview];
+        manifest.add_image(info);
+        added.push(image.key);
+    }
+
+    if !added.is_empty() {
+        // Rewrites keep the storage class and encryption the CLI gave the manifest
+        let stored = if exists {
+            manifest.update(&state.s3).await
+        } else {
+            manifest.store(&state.s3, manifest.expiry()).await
+        };
+        stored.map_err(|e| {
+            state.metrics.s3_error();
+            tracing::error!("Failed to store manifest for album {}: {:?}", album_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+
+    let total_images = manifest.images.len();
+    // Make the new images visible right away instead of after the cache TTL
+    state.manifest_cache.insert(&album_id, manifest);
+
+    Ok(Json(CommitResponse { album_id, total_images, added, missing }))
 }
 
 /// Get album manifest JSON
 pub async fn get_manifest(
     State(state): State<AppState>,
     Path(album_id): Path<String>,
+    Query(link): Query<LinkParams>,
+    jar: SignedCookieJar,
 ) -> Result<Json<AlbumManifest>, StatusCode> {
+    validate_album_id(&album_id)?;
     tracing::info!("Manifest API request: album_id={}", album_id);
+    state.metrics.manifest_request();
 
-    let manifest_key = format!("{album_id}/manifest.json");
-    let manifest_data = state
-        .s3
-        .download_file(&manifest_key)
+    let mut manifest = viewable_manifest(&state, &album_id, &link, &jar).await?;
+
+    // Generate presigned URLs for all images
+    presign_image_urls(&state, &album_id, &mut manifest).await;
+
+    Ok(Json(manifest))
+}
+
+/// Get album metadata (dimensions, filenames, hashes, tags) without any image URLs.
+/// Nothing is presigned, so integrations that only need metadata can poll this cheaply.
+pub async fn get_manifest_meta(
+    State(state): State<AppState>,
+    Path(album_id): Path<String>,
+    Query(link): Query<LinkParams>,
+    jar: SignedCookieJar,
+) -> Result<Json<AlbumManifest>, StatusCode> {
+    validate_album_id(&album_id)?;
+    tracing::info!("Manifest metadata request: album_id={}", album_id);
+    state.metrics.manifest_request();
+
+    let mut manifest = viewable_manifest(&state, &album_id, &link, &jar).await?;
+
+    // URLs stored by `gallery refresh-urls` are dropped too; they're skipped when serializing
+    for image in &mut manifest.images {
+        image.thumbnail_url = None;
+        image.preview_url = None;
+        image.original_url = None;
+        for variant in &mut image.preview_variants {
+            variant.url = None;
+        }
+    }
+
+    Ok(Json(manifest))
+}
+
+/// Fetch an album for an API client: 403 without a required signed link, 404 if missing,
+/// 410 once expired, 401 without the password. The password hash is cleared so it never reaches clients.
+async fn viewable_manifest(
+    state: &AppState,
+    album_id: &str,
+    link: &LinkParams,
+    jar: &SignedCookieJar,
+) -> Result<AlbumManifest, StatusCode> {
+    if !has_link_access(state, jar, album_id, link.token.as_deref(), link.exp.as_deref()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let mut manifest = cached_manifest(state, album_id)
         .await
+        .map(|m| AlbumManifest::clone(&m))
         .map_err(|e| {
+            state.metrics.s3_error();
             tracing::error!("Failed to fetch manifest for album {}: {:?}", album_id, e);
             StatusCode::NOT_FOUND
         })?;
 
-    let manifest_json = String::from_utf8(manifest_data).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let mut manifest: AlbumManifest =
-        serde_json::from_str(&manifest_json).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    // Generate presigned URLs for all images (valid for 7 days to match object expiration)
-    let expires_in = std::time::Duration::from_secs(7 * 24 * 3600);
-    for image in &mut manifest.images {
-        let thumbnail_key = format!("{album_id}/{}", image.thumbnail_path);
-        let preview_key = format!("{album_id}/{}", image.preview_path);
-        let original_key = format!("{album_id}/{}", image.original_path);
+    if manifest.is_expired() {
+        return Err(StatusCode::GONE);
+    }
 
-        image.thumbnail_url = state.s3.generate_presigned_url(&thumbnail_key, expires_in).await.ok();
-        image.preview_url = state.s3.generate_presigned_url(&preview_key, expires_in).await.ok();
-        image.original_url = state.s3.generate_presigned_url(&original_key, expires_in).await.ok();
+    if !is_authorized(jar, album_id, &manifest) {
+        return Err(StatusCode::UNAUTHORIZED);
     }
+    // Never expose the password hash to clients
+    manifest.password_hash = None;
 
-    Ok(Json(manifest))
+    Ok(manifest)
+}
+
+/// Path of the AVIF rendition of a JPEG preview/thumbnail, if the album has one
+fn avif_variant(manifest: &AlbumManifest, path: &str) -> Option<String> {
+    manifest.images.iter().find_map(|img| {
+        if img.preview_path == path {
+            img.preview_avif_path.clone()
+        } else if img.thumbnail_path == path {
+            img.thumbnail_avif_path.clone()
+        } else {
+            None
+        }
+    })
 }
 
 /// Get image from S3
 pub async fn get_image(
     State(state): State<AppState>,
     Path((album_id, path)): Path<(String, String)>,
-    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    jar: SignedCookieJar,
+    headers: HeaderMap,
+    Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response, StatusCode> {
+    validate_album_id(&album_id)?;
+    if !is_safe_object_path(&path) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
     tracing::info!("Image request: album_id={}, path={}", album_id, path);
+    state.metrics.image_request();
+
+    let (token, exp) = (params.get("token").map(String::as_str), params.get("exp").map(String::as_str));
+    if !has_link_access(&state, &jar, &album_id, token, exp) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Every tier of a protected album needs the password cookie, not just downloads;
+    // expired albums stop serving images just like their pages
+    let manifest = cached_manifest(&state, &album_id).await.map_err(|e| {
+        state.metrics.s3_error();
+        tracing::error!("Failed to fetch manifest for album {}: {:?}", album_id, e);
+        StatusCode::NOT_FOUND
+    })?;
+    if manifest.is_expired() {
+        return Err(StatusCode::GONE);
+    }
+    if !is_authorized(&jar, &album_id, &manifest) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // Only image tiers are served; the manifest (which may hold a password hash) is not
+    if !["thumbnails/", "previews/", "originals/"].iter().any(|tier| path.starts_with(tier)) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // Serve the AVIF rendition of a preview/thumbnail when the browser accepts it
+    let accepts_avif = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("image/avif"));
+    let negotiable = !path.starts_with("originals/");
+    let path = if negotiable && accepts_avif {
+        avif_variant(&manifest, &path).unwrap_or(path)
+    } else {
+        path
+    };
 
     let s3_key = format!("{album_id}/{path}");
     tracing::debug!("Computed S3 key: {}", s3_key);
 
-    let image_data = state
+    // Check if download is requested
+    let is_download = params
+        .get("download")
+        .is_some_and(|v| v == "1" || v == "true");
+    // View-only albums refuse downloads; this deters casual saving, it isn't DRM
+    if is_download && !manifest.allow_download {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Send the client straight to S3 instead of relaying the bytes: always for `?redirect=1`,
+    // and for originals when REDIRECT_ORIGINALS is set. Downloads stay proxied to keep the filename.
+    let redirect = match params.get("redirect").map(String::as_str) {
+        Some("1" | "true") => true,
+        Some(_) => false,
+        None => state.redirect_originals && path.starts_with("originals/"),
+    };
+    if redirect && !is_download {
+        let url = state.presigned_url(&s3_key).await.map_err(|e| {
+            state.metrics.s3_error();
+            tracing::error!("Failed to presign {}: {:?}", s3_key, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        let mut response = (StatusCode::FOUND, [(header::LOCATION, url)]).into_response();
+        if negotiable {
+            response.headers_mut().insert(header::VARY, header::HeaderValue::from_static("Accept"));
+        }
+        return Ok(response);
+    }
+
+    let object = state
         .s3
-        .download_file(&s3_key)
+        .open_stream(&s3_key)
         .await
         .map_err(|e| {
+            state.metrics.s3_error();
             tracing::error!("Failed to fetch image {}: {:?}", s3_key, e);
             StatusCode::NOT_FOUND
         })?;
 
-    // Determine content type
-    let content_type = if path.ends_with(".jpg") || path.ends_with(".jpeg") {
-        "image/jpeg"
-    } else if path.ends_with(".png") {
-        "image/png"
-    } else {
-        "application/octet-stream"
-    };
+    // Trust the type stored with the object; guess from the extension only when it has none
+    let content_type = object.content_type.unwrap_or_else(|| {
+        if path.ends_with(".jpg") || path.ends_with(".jpeg") {
+            "image/jpeg"
+        } else if path.ends_with(".png") {
+            "image/png"
+        } else if path.ends_with(".avif") {
+            "image/avif"
+        } else {
+            "application/octet-stream"
+        }
+        .to_string()
+    });
 
-    tracing::debug!("Serving image: s3_key={}, content_type={}, size={} bytes", s3_key, content_type, image_data.len());
+    tracing::debug!("Serving image: s3_key={}, content_type={}", s3_key, content_type);
 
-    // Check if download is requested
-    let is_download = params.get("download").map(|v| v == "true").unwrap_or(false);
+    // Relay bytes to the client as they arrive from S3, counting them on the way
+    let metrics = Arc::clone(&state.metrics);
+    let body = Body::from_stream(ReaderStream::new(object.body.into_async_read()).inspect(move |chunk| {
+        if let Ok(bytes) = chunk {
+            metrics.bytes_served(bytes.len() as u64);
+        }
+    }));
 
     if is_download {
-        // Extract filename from path
-        let filename = path.split('/').next_back().unwrap_or("image.jpg");
+        // Map the UUID-based key back to the uploader's filename
+        let filename = manifest
+            .images
+            .iter()
+            .find(|img| [&img.original_path, &img.preview_path, &img.thumbnail_path].contains(&&path))
+            .map(|img| img.original_filename.clone())
+            .unwrap_or_else(|| path.split('/').next_back().unwrap_or("image.jpg").to_string());
+
         Ok((
             [
                 (header::CONTENT_TYPE, content_type),
-                (header::CONTENT_DISPOSITION, &format!("attachment; filename=\"{filename}\"")),
+                (header::CONTENT_DISPOSITION, attachment_disposition(&filename)),
             ],
-            image_data,
+            body,
         )
             .into_response())
+    } else if negotiable {
+        // Caches must key previews/thumbnails on Accept since the format depends on it
+        Ok(([(header::CONTENT_TYPE, content_type), (header::VARY, "Accept".to_string())], body).into_response())
     } else {
-        Ok(([(header::CONTENT_TYPE, content_type)], image_data).into_response())
-    }
-}
-
-fn generate_gallery_html(album_id: &str, manifest: &AlbumManifest) -> String {
-    format!(
-        r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0, maximum-scale=1.0, user-scalable=no">
-    <meta name="apple-mobile-web-app-capable" content="yes">
-    <meta name="apple-mobile-web-app-status-bar-style" content="black-translucent">
-    <title>{album_name} - Film Gallery</title>
-    <style>
-        * {{
-            margin: 0;
-            padding: 0;
-            box-sizing: border-box;
-        }}
-
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            background: #ffffff;
-            color: #333;
-            line-height: 1.6;
-            /* Safe area insets for notched devices */
-            padding-top: env(safe-area-inset-top);
-        }}
-
-        body.lightbox-open {{
-            overflow: hidden;
-            position: fixed;
-            width: 100%;
-        }}
-
-        .header {{
-            padding: 40px 20px;
-            text-align: center;
-            border-bottom: 1px solid #eee;
-        }}
-
-        .header h1 {{
-            font-size: 2.5rem;
-            font-weight: 300;
-            margin-bottom: 10px;
-        }}
-
-        .header p {{
-            color: #666;
-            font-size: 0.9rem;
-        }}
-
-        .gallery-container {{
-            max-width: 1400px;
-            margin: 0 auto;
-            padding: 40px 20px;
-        }}
-
-        /* Centered justified gallery layout */
-        .bento-grid {{
-            display: flex;
-            flex-wrap: wrap;
-            justify-content: center;
-            gap: 15px;
-            align-items: center;
-        }}
-
-        .bento-item {{
-            position: relative;
-            cursor: pointer;
-            background: #f5f5f5;
-            border-radius: 4px;
-            transition: transform 0.2s ease;
-            flex: 0 0 auto;
-            max-height: 300px;
-        }}
-
-        .bento-item:hover {{
-            transform: translateY(-4px);
-            box-shadow: 0 8px 20px rgba(0,0,0,0.1);
-        }}
-
-        .bento-item img {{
-            display: block;
-            height: 300px;
-            width: auto;
-            object-fit: contain;
-            border-radius: 4px;
-            transition: opacity 0.3s ease;
-        }}
-
-        .bento-item img.loading {{
-            opacity: 0.7;
-        }}
-
-        /* Lightbox */
-        .lightbox {{
-            display: none;
-            position: fixed;
-            top: 0;
-            left: 0;
-            width: 100%;
-            height: 100%;
-            background: rgba(0, 0, 0, 0.97);
-            z-index: 1000;
-            align-items: center;
-            justify-content: center;
-            opacity: 0;
-            transition: opacity 0.3s ease;
-        }}
-
-        .lightbox.active {{
-            display: flex;
-            opacity: 1;
-        }}
-
-        .lightbox-content {{
-            position: relative;
-            width: 90vw;
-            height: 90vh;
-            display: flex;
-            align-items: center;
-            justify-content: center;
-        }}
-
-        .lightbox-image {{
-            width: 100%;
-            height: 100%;
-            object-fit: contain;
-            user-select: none;
-            transition: opacity 0.2s ease;
-            touch-action: pan-x pan-y;
-            -webkit-touch-callout: none;
-        }}
-
-        /* Navigation arrows */
-        .nav-btn {{
-            position: fixed;
-            top: 50%;
-            transform: translateY(-50%);
-            background: rgba(255, 255, 255, 0.1);
-            border: none;
-            width: 60px;
-            height: 60px;
-            cursor: pointer;
-            font-size: 2rem;
-            color: white;
-            border-radius: 50%;
-            z-index: 1001;
-            transition: all 0.2s ease;
-            backdrop-filter: blur(10px);
-            display: flex;
-            align-items: center;
-            justify-content: center;
-        }}
-
-        .nav-btn:hover {{
-            background: rgba(255, 255, 255, 0.2);
-            transform: translateY(-50%) scale(1.1);
-        }}
-
-        .nav-btn:active {{
-            transform: translateY(-50%) scale(0.95);
-        }}
-
-        .nav-btn.prev {{
-            left: 20px;
-        }}
-
-        .nav-btn.next {{
-            right: 20px;
-        }}
-
-        .nav-btn:disabled {{
-            opacity: 0.3;
-            cursor: not-allowed;
-        }}
-
-        .nav-btn:disabled:hover {{
-            transform: translateY(-50%);
-            background: rgba(255, 255, 255, 0.1);
-        }}
-
-        /* Top controls */
-        .lightbox-controls {{
-            position: fixed;
-            top: 20px;
-            right: 20px;
-            display: flex;
-            gap: 10px;
-            z-index: 1001;
-            /* Safe area for notched devices */
-            top: max(20px, env(safe-area-inset-top));
-            right: max(20px, env(safe-area-inset-right));
-        }}
-
-        .lightbox-btn {{
-            background: rgba(255, 255, 255, 0.1);
-            border: none;
-            padding: 12px 20px;
-            cursor: pointer;
-            font-size: 0.9rem;
-            color: white;
-            border-radius: 6px;
-            transition: all 0.2s ease;
-            backdrop-filter: blur(10px);
-            font-weight: 500;
-            min-height: 44px;
-        }}
-
-        .lightbox-btn:hover {{
-            background: rgba(255, 255, 255, 0.2);
-        }}
-
-        .lightbox-btn:active {{
-            transform: scale(0.95);
-        }}
-
-        .close-btn {{
-            position: fixed;
-            top: 20px;
-            left: 20px;
-            background: rgba(255, 255, 255, 0.1);
-            border: none;
-            width: 44px;
-            height: 44px;
-            cursor: pointer;
-            font-size: 1.5rem;
-            color: white;
-            border-radius: 6px;
-            z-index: 1001;
-            transition: all 0.2s ease;
-            backdrop-filter: blur(10px);
-            display: flex;
-            align-items: center;
-            justify-content: center;
-            /* Safe area for notched devices */
-            top: max(20px, env(safe-area-inset-top));
-            left: max(20px, env(safe-area-inset-left));
-        }}
-
-        .close-btn:hover {{
-            background: rgba(255, 255, 255, 0.2);
-        }}
-
-        .close-btn:active {{
-            transform: scale(0.95);
-        }}
-
-        /* Image counter */
-        .image-counter {{
-            position: fixed;
-            bottom: 30px;
-            left: 50%;
-            transform: translateX(-50%);
-            background: rgba(255, 255, 255, 0.1);
-            padding: 8px 20px;
-            border-radius: 20px;
-            color: white;
-            font-size: 0.9rem;
-            z-index: 1001;
-            backdrop-filter: blur(10px);
-            font-weight: 500;
-            /* Safe area for devices with bottom insets */
-            bottom: max(30px, env(safe-area-inset-bottom));
-        }}
-
-        @media (max-width: 768px) {{
-            .header h1 {{
-                font-size: 2rem;
-            }}
-
-            .bento-grid {{
-                flex-direction: column;
-                align-items: stretch;
-            }}
-
-            .bento-item {{
-                max-height: none;
-                width: 100%;
-            }}
-
-            .bento-item img {{
-                width: 100%;
-                height: auto;
-            }}
-
-            /* Hide navigation arrows on mobile - use swipe instead */
-            .nav-btn {{
-                display: none;
-            }}
-
-            /* Larger touch targets on mobile */
-            .close-btn {{
-                width: 48px;
-                height: 48px;
-            }}
-
-            .lightbox-btn {{
-                min-height: 48px;
-                padding: 14px 24px;
-            }}
-
-            .image-counter {{
-                font-size: 1rem;
-                padding: 10px 24px;
-            }}
-        }}
-    </style>
-</head>
-<body>
-    <div class="header">
-        <h1>{album_name}</h1>
-        <p>{image_count} photographs</p>
-    </div>
-
-    <div class="gallery-container">
-        <div class="bento-grid" id="gallery">
-            {thumbnails}
-        </div>
-    </div>
-
-    <div class="lightbox" id="lightbox">
-        <button class="close-btn" onclick="closeLightbox()">&times;</button>
-        <button class="nav-btn prev" id="prev-btn" onclick="navigateImage(-1)">‹</button>
-        <button class="nav-btn next" id="next-btn" onclick="navigateImage(1)">›</button>
-        <div class="lightbox-controls">
-            <button class="lightbox-btn" onclick="downloadImage()">Download</button>
-        </div>
-        <div class="image-counter" id="image-counter">1 / 1</div>
-        <div class="lightbox-content">
-            <img class="lightbox-image" id="lightbox-img" src="" alt="">
-        </div>
-    </div>
-
-    <script>
-        const albumId = '{album_id}';
-        const images = {images_json};
-        let currentImageIndex = 0;
-
-        // Track which images have which tiers loaded
-        const loadedTiers = {{}};
-
-        // Cache for preloaded Image objects to prevent garbage collection
-        const imageCache = {{}};
-
-        // Progressive enhancement: upgrade thumbnails to previews in the gallery
-        document.addEventListener('DOMContentLoaded', () => {{
-            images.forEach((image, index) => {{
-                const previewUrl = image.preview_url || `/api/album/${{albumId}}/image/${{image.preview_path}}`;
-                const thumbImg = document.querySelector(`img[data-index="${{index}}"]`);
-
-                if (thumbImg && previewUrl) {{
-                    const previewImg = new Image();
-                    previewImg.onload = () => {{
-                        // Direct swap - no flashing fade animation
-                        thumbImg.src = previewImg.src;
-
-                        if (!loadedTiers[index]) loadedTiers[index] = {{}};
-                        loadedTiers[index].preview = true;
-                    }};
-                    previewImg.src = previewUrl;
-                }}
-            }});
-        }});
-
-        function openLightbox(index) {{
-            currentImageIndex = index;
-            showImage(index);
-            document.getElementById('lightbox').classList.add('active');
-            document.body.classList.add('lightbox-open');
-            updateNavButtons();
-            preloadAdjacentImages();
-        }}
-
-        function showImage(index) {{
-            const image = images[index];
-            const lightboxImg = document.getElementById('lightbox-img');
-            const counter = document.getElementById('image-counter');
-
-            const tiers = loadedTiers[index] || {{}};
-            const originalUrl = image.original_url || `/api/album/${{albumId}}/image/${{image.original_path}}`;
-            const previewUrl = image.preview_url || `/api/album/${{albumId}}/image/${{image.preview_path}}`;
-            const thumbnailUrl = image.thumbnail_url || `/api/album/${{albumId}}/image/${{image.thumbnail_path}}`;
-
-            // Update counter
-            counter.textContent = `${{index + 1}} / ${{images.length}}`;
-
-            // If original is already loaded, show it immediately - no re-download
-            if (tiers.original) {{
-                lightboxImg.style.opacity = '1';
-                lightboxImg.src = originalUrl;
-                return;
-            }}
-
-            // Determine best available tier to show while loading original
-            let initialSrc = thumbnailUrl;
-            if (tiers.preview || image.preview_url) {{
-                initialSrc = previewUrl;
-            }}
-
-            // Show best available tier immediately
-            lightboxImg.style.opacity = '1';
-            lightboxImg.src = initialSrc;
-
-            // If showing thumbnail and preview not loaded yet, load preview first
-            if (initialSrc === thumbnailUrl && !tiers.preview && previewUrl) {{
-                const previewImg = new Image();
-                previewImg.onload = () => {{
-                    lightboxImg.style.opacity = '0.3';
-                    setTimeout(() => {{
-                        lightboxImg.src = previewImg.src;
-                        lightboxImg.style.opacity = '1';
-                    }}, 50);
-                    if (!loadedTiers[index]) loadedTiers[index] = {{}};
-                    loadedTiers[index].preview = true;
-                }};
-                previewImg.src = previewUrl;
-            }}
-
-            // Load original in background and swap when ready
-            const fullImg = new Image();
-            fullImg.onload = () => {{
-                // Smooth transition to full-res
-                lightboxImg.style.opacity = '0.5';
-                setTimeout(() => {{
-                    lightboxImg.src = fullImg.src;
-                    lightboxImg.style.opacity = '1';
-                }}, 50);
-                if (!loadedTiers[index]) loadedTiers[index] = {{}};
-                loadedTiers[index].original = true;
-
-                // Cache the image object to prevent garbage collection
-                if (!imageCache[index]) imageCache[index] = {{}};
-                imageCache[index].original = fullImg;
-            }};
-            fullImg.src = originalUrl;
-        }}
-
-        function navigateImage(direction) {{
-            const newIndex = currentImageIndex + direction;
-            if (newIndex >= 0 && newIndex < images.length) {{
-                currentImageIndex = newIndex;
-                showImage(newIndex);
-                updateNavButtons();
-                preloadAdjacentImages();
-            }}
-        }}
-
-        function updateNavButtons() {{
-            const prevBtn = document.getElementById('prev-btn');
-            const nextBtn = document.getElementById('next-btn');
-            prevBtn.disabled = currentImageIndex === 0;
-            nextBtn.disabled = currentImageIndex === images.length - 1;
-        }}
-
-        function preloadAdjacentImages() {{
-            // Preload next and previous originals
-            [-1, 1].forEach(offset => {{
-                const idx = currentImageIndex + offset;
-                if (idx >= 0 && idx < images.length) {{
-                    const tiers = loadedTiers[idx] || {{}};
-
-                    // Skip if already loaded
-                    if (tiers.original) return;
-
-                    const img = images[idx];
-                    const originalUrl = img.original_url || `/api/album/${{albumId}}/image/${{img.original_path}}`;
-                    const preloadImg = new Image();
-                    preloadImg.onload = () => {{
-                        if (!loadedTiers[idx]) loadedTiers[idx] = {{}};
-                        loadedTiers[idx].original = true;
-
-                        // Store in cache to prevent garbage collection
-                        if (!imageCache[idx]) imageCache[idx] = {{}};
-                        imageCache[idx].original = preloadImg;
-                    }};
-                    preloadImg.src = originalUrl;
-                }}
-            }});
-        }}
-
-        function closeLightbox() {{
-            document.getElementById('lightbox').classList.remove('active');
-            document.body.classList.remove('lightbox-open');
-        }}
-
-        function downloadImage() {{
-            const image = images[currentImageIndex];
-            // Use proxy endpoint with download parameter to get proper Content-Disposition header
-            const downloadUrl = `/api/album/${{albumId}}/image/${{image.original_path}}?download=true`;
-
-            // Create temporary link and trigger download
-            const link = document.createElement('a');
-            link.href = downloadUrl;
-            link.download = image.original_filename;
-            document.body.appendChild(link);
-            link.click();
-            document.body.removeChild(link);
-        }}
-
-        // Keyboard shortcuts
-        document.addEventListener('keydown', (e) => {{
-            const lightbox = document.getElementById('lightbox');
-            if (!lightbox.classList.contains('active')) return;
-
-            if (e.key === 'Escape') {{
-                closeLightbox();
-            }} else if (e.key === 'ArrowLeft') {{
-                navigateImage(-1);
-            }} else if (e.key === 'ArrowRight') {{
-                navigateImage(1);
-            }}
-        }});
-
-        // Close on background click
-        document.getElementById('lightbox').addEventListener('click', (e) => {{
-            if (e.target.id === 'lightbox') closeLightbox();
-        }});
-
-        // Mobile swipe navigation
-        let touchStartX = 0;
-        let touchEndX = 0;
-        const lightboxContent = document.querySelector('.lightbox-content');
-
-        lightboxContent.addEventListener('touchstart', (e) => {{
-            touchStartX = e.changedTouches[0].screenX;
-        }}, false);
-
-        lightboxContent.addEventListener('touchend', (e) => {{
-            touchEndX = e.changedTouches[0].screenX;
-            handleSwipe();
-        }}, false);
-
-        function handleSwipe() {{
-            const swipeThreshold = 50;
-            const diff = touchStartX - touchEndX;
-
-            if (Math.abs(diff) > swipeThreshold) {{
-                if (diff > 0) {{
-                    // Swiped left - next image
-                    navigateImage(1);
-                }} else {{
-                    // Swiped right - previous image
-                    navigateImage(-1);
-                }}
-            }}
-        }}
-    </script>
-</body>
-</html>"#,
-        album_name = html_escape(&manifest.name),
-        album_id = album_id,
-        image_count = manifest.images.len(),
-        thumbnails = generate_thumbnails_html(album_id, manifest),
-        images_json = serde_json::to_string(&manifest.images).unwrap_or_else(|_| "[]".to_string()),
-    )
+        Ok(([(header::CONTENT_TYPE, content_type)], body).into_response())
+    }
 }
 
-fn generate_thumbnails_html(album_id: &str, manifest: &AlbumManifest) -> String {
-    manifest
-        .images
-        .iter()
-        .enumerate()
-        .map(|(index, image)| {
-            let thumbnail_src = image
-                .thumbnail_url
-                .clone()
-                .unwrap_or_else(|| {
-                    // Fallback to proxigned URL if presigned URL not available
-                    format!("/api/album/{}/image/{}", album_id, image.thumbnail_path)
-                });
-
-            format!(
-                r#"<div class="bento-item" onclick="openLightbox({index})">
-                <img data-index="{index}" src="{thumbnail_src}" alt="{filename}" loading="lazy">
-            </div>"#,
-                index = index,
-                thumbnail_src = html_escape(&thumbnail_src),
-                filename = html_escape(&image.original_filename),
-            )
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
-
-fn html_escape(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
-}
-
-fn generate_404_html() -> String {
-    r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>Gallery Not Found</title>
-    <style>
-        body {
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            display: flex;
-            align-items: center;
-            justify-content: center;
-            min-height: 100vh;
-            margin: 0;
-            background: #ffffff;
-            color: #333;
-        }
-        .container {
-            text-align: center;
-            padding: 40px 20px;
-            max-width: 500px;
-        }
-        h1 {
-            font-size: 6rem;
-            font-weight: 300;
-            margin: 0;
-            color: #999;
-        }
-        p {
-            font-size: 1.2rem;
-            margin: 20px 0;
-            color: #666;
-        }
-        a {
-            color: #333;
-            text-decoration: none;
-            border-bottom: 1px solid #333;
-        }
-        a:hover {
-            border-bottom: 2px solid #333;
-        }
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>404</h1>
-        <p>This gallery doesn't exist or has expired.</p>
-        <p><a href="/">Return home</a></p>
-    </div>
-</body>
-</html>"#.to_string()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn object_paths_stay_inside_the_album() {
+        assert!(is_safe_object_path("previews/0b4fd2c8-3f7e-4d1a-9c55-6e2a7b8d9f01.jpg"));
+        assert!(is_safe_object_path("originals/0b4fd2c8-3f7e-4d1a-9c55-6e2a7b8d9f01.jpg"));
+
+        assert!(!is_safe_object_path("../other-album/manifest.json"));
+        assert!(!is_safe_object_path("previews/../../other-album/manifest.json"));
+        assert!(!is_safe_object_path("previews/./photo.jpg"));
+        assert!(!is_safe_object_path("%2e%2e/other-album/manifest.json"));
+        assert!(!is_safe_object_path("previews/%2E%2E/manifest.json"));
+        assert!(!is_safe_object_path("/previews/photo.jpg"));
+        assert!(!is_safe_object_path("previews\\..\\manifest.json"));
+        assert!(!is_safe_object_path("previews//photo.jpg"));
+        assert!(!is_safe_object_path("previews/"));
+        assert!(!is_safe_object_path(""));
+    }
+
+    #[test]
+    fn password_change_revokes_access_cookies() {
+        let album_id = "4f6a0d3e-2c1b-4e8a-9d7f-1a2b3c4d5e6f";
+        let mut manifest = AlbumManifest::with_id("Private".to_string(), album_id.to_string());
+        manifest.password_hash = Some(password::hash_password("first").unwrap());
+
+        let fingerprint = password::hash_fingerprint(manifest.password_hash.as_ref().unwrap());
+        let cookie = Cookie::new(auth_cookie_name(album_id), fingerprint);
+        let jar = SignedCookieJar::new(axum_extra::extract::cookie::Key::generate()).add(cookie);
+        assert!(is_authorized(&jar, album_id, &manifest));
+
+        // Cookies issued before the change, or holding just the album ID, no longer work
+        manifest.password_hash = Some(password::hash_password("second").unwrap());
+        assert!(!is_authorized(&jar, album_id, &manifest));
+        let legacy = jar.clone().add(Cookie::new(auth_cookie_name(album_id), album_id));
+        assert!(!is_authorized(&legacy, album_id, &manifest));
+
+        manifest.password_hash = None;
+        assert!(is_authorized(&jar, album_id, &manifest));
+    }
 }
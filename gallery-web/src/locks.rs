@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// One lock per album, serializing read-modify-write updates of its manifest within this
+/// server. Writes from the CLI or other server instances aren't covered
+#[derive(Default)]
+pub struct AlbumLocks {
+    locks: Mutex<HashMap<String, Weak<AsyncMutex<()>>>>,
+}
+
+impl AlbumLocks {
+    /// Wait for exclusive access to an album; held until the guard is dropped
+    pub async fn lock(&self, album_id: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            // Locks nobody holds or waits for anymore are dropped along the way
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            match locks.get(album_id).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(AsyncMutex::new(()));
+                    locks.insert(album_id.to_string(), Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+}
@@ -1,53 +1,127 @@
+mod access_log;
+mod cache;
 mod handlers;
+mod locks;
+mod metrics;
+mod rate_limit;
+mod server;
 mod state;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use axum::{
-    routing::get,
+    http::{header, HeaderValue, Method},
+    middleware,
+    routing::{get, post},
     Router,
 };
 use std::env;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use std::net::{IpAddr, SocketAddr};
+use tower_http::{
+    compression::{
+        predicate::{NotForContentType, Predicate, SizeAbove},
+        CompressionLayer,
+    },
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::state::AppState;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
+    // Initialize tracing; GALLERY_LOG_FORMAT=json emits one JSON object per line
+    let json_logs = std::env::var("GALLERY_LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json"));
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "gallery_web=info,gallery_core=debug,tower_http=debug".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json_logs).then(tracing_subscriber::fmt::layer))
         .init();
 
     // Get configuration from environment
     let bucket = env::var("GALLERY_BUCKET")
         .expect("GALLERY_BUCKET environment variable must be set");
     let port = env::var("PORT").unwrap_or_else(|_| "3000".to_string());
+    let bind_addr = env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0".to_string());
+
+    let ip: IpAddr = bind_addr
+        .parse()
+        .with_context(|| format!("BIND_ADDR is not a valid IP address: {bind_addr:?}"))?;
+    let port: u16 = port
+        .parse()
+        .with_context(|| format!("PORT is not a valid port number: {port:?}"))?;
+    let addr = SocketAddr::new(ip, port);
 
     // Create app state
     let state = AppState::new(bucket).await?;
+    let server_settings = server::ServerSettings::from_env()?;
+
+    // API routes hit S3 on every request and password checks run Argon2, so they are rate limited per client
+    let api = Router::new()
+        .route("/gallery/:album_id/auth", post(handlers::gallery_auth))
+        .route("/api/album/:album_id/manifest", get(handlers::get_manifest))
+        .route("/api/album/:album_id/meta", get(handlers::get_manifest_meta))
+        .route("/api/album/:album_id/presign", post(handlers::presign_upload))
+        .route("/api/album/:album_id/commit", post(handlers::commit_upload))
+        .route("/api/album/:album_id/image/*path", get(handlers::get_image))
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit));
 
     // Build router
     let app = Router::new()
         .route("/", get(handlers::index))
+        .route("/favicon.ico", get(handlers::favicon))
+        .route("/icon-192.png", get(handlers::icon_192))
+        .route("/icon-512.png", get(handlers::icon_512))
+        .route("/manifest.webmanifest", get(handlers::web_manifest))
+        .route("/healthz", get(handlers::healthz))
+        .route("/readyz", get(handlers::readyz))
+        .route("/metrics", get(handlers::metrics))
+        .route("/albums", get(handlers::albums))
         .route("/gallery/:album_id", get(handlers::gallery))
-        .route("/api/album/:album_id/manifest", get(handlers::get_manifest))
-        .route("/api/album/:album_id/image/*path", get(handlers::get_image))
-        .layer(CorsLayer::permissive())
+        .merge(api)
+        // Compress HTML/JSON; image bytes are already compressed so skip them
+        .layer(
+            CompressionLayer::new()
+                .compress_when(SizeAbove::default().and(NotForContentType::IMAGES)),
+        )
+        .layer(cors_layer()?)
         .layer(TraceLayer::new_for_http())
+        // Outermost, so sizes are what actually went over the wire after compression
+        .layer(middleware::from_fn(access_log::log))
         .with_state(state);
 
     // Start server
-    let addr = format!("0.0.0.0:{port}");
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to bind to {addr}"))?;
 
     tracing::info!("Gallery web server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    server::serve(listener, app, server_settings).await?;
 
     Ok(())
 }
+
+/// CORS restricted to the comma-separated ALLOWED_ORIGINS, or permissive when it is unset
+fn cors_layer() -> Result<CorsLayer> {
+    let Ok(allowed) = env::var("ALLOWED_ORIGINS") else {
+        return Ok(CorsLayer::permissive());
+    };
+
+    let origins = allowed
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/'))
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            HeaderValue::from_str(origin).with_context(|| format!("ALLOWED_ORIGINS has an invalid origin: {origin:?}"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]))
+}
@@ -0,0 +1,46 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Request counters exposed at `/metrics` in the Prometheus text format
+#[derive(Default)]
+pub struct Metrics {
+    image_requests: AtomicU64,
+    manifest_requests: AtomicU64,
+    bytes_served: AtomicU64,
+    s3_errors: AtomicU64,
+}
+
+impl Metrics {
+    pub fn image_request(&self) {
+        self.image_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn manifest_request(&self) {
+        self.manifest_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn bytes_served(&self, bytes: u64) {
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn s3_error(&self) {
+        self.s3_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let counters = [
+            ("gallery_image_requests_total", "Image requests served through the API", &self.image_requests),
+            ("gallery_manifest_requests_total", "Manifest API requests", &self.manifest_requests),
+            ("gallery_bytes_served_total", "Image bytes streamed to clients", &self.bytes_served),
+            ("gallery_s3_errors_total", "Failed S3 requests", &self.s3_errors),
+        ];
+
+        let mut out = String::new();
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {}", value.load(Ordering::Relaxed));
+        }
+        out
+    }
+}
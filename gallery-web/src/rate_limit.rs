@@ -0,0 +1,91 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::state::AppState;
+
+/// Clients idle for this long are forgotten when the table is pruned
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Per-client-IP token bucket allowing `rate` requests per second with bursts of twice that
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<IpAddr, (Instant, f64)>>,
+    rate: f64,
+    burst: f64,
+    /// Key clients by the last `X-Forwarded-For` hop instead of the socket address
+    trust_forwarded: bool,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32, trust_forwarded: bool) -> Self {
+        let rate = f64::from(requests_per_second);
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            rate,
+            burst: rate * 2.0,
+            trust_forwarded,
+        }
+    }
+
+    /// Take a token for `ip`, returning false if its bucket is empty
+    pub fn check(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() > 10_000 {
+            buckets.retain(|_, (last_seen, _)| now.duration_since(*last_seen) < IDLE_TIMEOUT);
+        }
+
+        let (last_seen, tokens) = buckets.entry(ip).or_insert((now, self.burst));
+        let refill = now.duration_since(*last_seen).as_secs_f64() * self.rate;
+        *tokens = (*tokens + refill).min(self.burst);
+        *last_seen = now;
+
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn client_ip(&self, headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+        if self.trust_forwarded {
+            // The last hop is the one appended by our own proxy; earlier ones are client-supplied
+            let forwarded = headers
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.rsplit(',').next())
+                .and_then(|hop| hop.trim().parse().ok());
+            if let Some(ip) = forwarded {
+                return ip;
+            }
+        }
+        peer.ip()
+    }
+}
+
+/// Middleware rejecting clients that exceed the configured request rate
+pub async fn limit(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(limiter) = &state.rate_limiter {
+        let ip = limiter.client_ip(request.headers(), peer);
+        if !limiter.check(ip) {
+            tracing::warn!("Rate limit exceeded for {}", ip);
+            return StatusCode::TOO_MANY_REQUESTS.into_response();
+        }
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use axum::{body::Body, extract::ConnectInfo, http::Request, Router};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto::Builder,
+    service::TowerToHyperService,
+};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower::ServiceExt;
+
+/// Connection tuning read from the environment; hyper's defaults for anything unset
+pub struct ServerSettings {
+    /// Requests one HTTP/2 connection may have in flight at once (HTTP2_MAX_CONCURRENT_STREAMS)
+    http2_max_concurrent_streams: Option<u32>,
+    /// Interval of HTTP/2 pings that detect dead connections (HTTP2_KEEPALIVE_INTERVAL_SECS); off when unset
+    http2_keep_alive_interval: Option<Duration>,
+    /// Reuse HTTP/1.1 connections between requests; on unless HTTP1_KEEPALIVE=0
+    http1_keep_alive: bool,
+}
+
+impl ServerSettings {
+    pub fn from_env() -> Result<Self> {
+        let http2_max_concurrent_streams = match std::env::var("HTTP2_MAX_CONCURRENT_STREAMS") {
+            Ok(value) => Some(value.parse::<u32>().ok().filter(|&streams| streams > 0).ok_or_else(|| {
+                anyhow::anyhow!("HTTP2_MAX_CONCURRENT_STREAMS must be a positive whole number, got {value:?}")
+            })?),
+            Err(_) => None,
+        };
+
+        let http2_keep_alive_interval = match std::env::var("HTTP2_KEEPALIVE_INTERVAL_SECS") {
+            Ok(value) => {
+                let secs = value.parse::<u64>().map_err(|_| {
+                    anyhow::anyhow!("HTTP2_KEEPALIVE_INTERVAL_SECS must be a number of seconds, got {value:?}")
+                })?;
+                (secs > 0).then(|| Duration::from_secs(secs))
+            }
+            Err(_) => None,
+        };
+
+        let http1_keep_alive = !std::env::var("HTTP1_KEEPALIVE").is_ok_and(|v| v == "0" || v == "false");
+
+        Ok(Self {
+            http2_max_concurrent_streams,
+            http2_keep_alive_interval,
+            http1_keep_alive,
+        })
+    }
+}
+
+/// Serve `app` on `listener`, speaking HTTP/1.1 or cleartext HTTP/2 (h2c, prior knowledge) on each
+/// connection as the client chooses. Handlers see the peer address as `ConnectInfo<SocketAddr>`.
+pub async fn serve(listener: TcpListener, app: Router, settings: ServerSettings) -> Result<()> {
+    let mut builder = Builder::new(TokioExecutor::new());
+    builder.http1().keep_alive(settings.http1_keep_alive);
+    builder.http2().keep_alive_interval(settings.http2_keep_alive_interval);
+    // Passing `None` would lift hyper's default limit rather than keep it
+    if let Some(streams) = settings.http2_max_concurrent_streams {
+        builder.http2().max_concurrent_streams(streams);
+    }
+
+    loop {
+        let (stream, remote_addr) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Usually out of file descriptors; give open connections a moment to close
+                tracing::error!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let service = app.clone().map_request(move |mut request: Request<Incoming>| {
+            request.extensions_mut().insert(ConnectInfo(remote_addr));
+            request.map(Body::new)
+        });
+        let builder = builder.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = builder
+                .serve_connection(TokioIo::new(stream), TowerToHyperService::new(service))
+                .await
+            {
+                // Mostly clients disconnecting before sending a request
+                tracing::debug!("Connection from {} closed with an error: {}", remote_addr, e);
+            }
+        });
+    }
+}
@@ -1,15 +1,161 @@
 use anyhow::Result;
-use gallery_core::S3Client;
+use axum::extract::FromRef;
+use axum_extra::extract::cookie::Key;
+use gallery_core::{ClientOptions, S3Client};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::ManifestCache;
+use crate::locks::AlbumLocks;
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
+
+/// S3 rejects presigned URLs valid for longer than 7 days
+const MAX_PRESIGN_EXPIRY_SECS: u64 = 7 * 24 * 3600;
+
+/// How long a parsed manifest is reused before re-fetching from S3, unless MANIFEST_CACHE_TTL_SECS overrides it
+const DEFAULT_MANIFEST_CACHE_TTL_SECS: u64 = 60;
+
+/// Per-IP request rate for the /api routes and password attempts unless RATE_LIMIT_RPS overrides it
+const DEFAULT_RATE_LIMIT_RPS: u32 = 50;
 
 #[derive(Clone)]
 pub struct AppState {
     pub s3: S3Client,
+    /// Public origin of this server (e.g. `https://photos.example.com`), used for absolute links
+    pub public_base_url: Option<String>,
+    /// Lifetime of presigned image URLs handed to browsers
+    pub presign_expiry: Duration,
+    /// CDN origin (e.g. CloudFront) that presigned image URLs are pointed at instead of S3
+    pub cdn_base_url: Option<String>,
+    /// Secret that gallery links must be signed with (`gallery sign-url`); any link works when unset
+    pub signing_key: Option<Vec<u8>>,
+    /// Bearer token required for direct-to-S3 upload endpoints; disabled when unset
+    pub upload_token: Option<String>,
+    /// Bearer token required to scrape /metrics; open to anyone when unset
+    pub metrics_token: Option<String>,
+    /// Signs album access cookies for password-protected galleries
+    pub cookie_key: Key,
+    pub manifest_cache: Arc<ManifestCache>,
+    /// Held while an upload commit rewrites an album's manifest, so concurrent commits don't drop images
+    pub album_locks: Arc<AlbumLocks>,
+    /// Per-client limit on /api requests and password attempts; disabled with RATE_LIMIT_RPS=0
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub metrics: Arc<Metrics>,
+    /// Serve a public list of albums at /albums (ENABLE_ALBUM_INDEX=1)
+    pub album_index: bool,
+    /// Answer proxied requests for originals with a redirect to S3 (REDIRECT_ORIGINALS=1)
+    pub redirect_originals: bool,
 }
 
 impl AppState {
     pub async fn new(bucket: String) -> Result<Self> {
-        let s3 = S3Client::new(bucket).await?;
+        let originals_bucket = std::env::var("GALLERY_ORIGINALS_BUCKET").ok().filter(|b| !b.is_empty());
+        let key_prefix = std::env::var("GALLERY_PREFIX").ok();
+        let s3 = S3Client::new_with_options(bucket, ClientOptions { originals_bucket, key_prefix, ..Default::default() }).await?;
+
+        let public_base_url = std::env::var("PUBLIC_BASE_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .filter(|url| !url.is_empty());
+
+        let presign_expiry_secs = match std::env::var("PRESIGN_EXPIRY_SECS") {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|_| anyhow::anyhow!("PRESIGN_EXPIRY_SECS must be a number of seconds, got {value:?}"))?,
+            Err(_) => MAX_PRESIGN_EXPIRY_SECS,
+        };
+        if presign_expiry_secs > MAX_PRESIGN_EXPIRY_SECS {
+            tracing::warn!(
+                "PRESIGN_EXPIRY_SECS={} exceeds the S3 maximum of {}; clamping",
+                presign_expiry_secs,
+                MAX_PRESIGN_EXPIRY_SECS
+            );
+        }
+        let presign_expiry = Duration::from_secs(presign_expiry_secs.min(MAX_PRESIGN_EXPIRY_SECS));
+
+        let cdn_base_url = match std::env::var("CDN_BASE_URL") {
+            Ok(url) if url.starts_with("https://") || url.starts_with("http://") => {
+                Some(url.trim_end_matches('/').to_string())
+            }
+            Ok(url) if url.is_empty() => None,
+            Ok(url) => anyhow::bail!("CDN_BASE_URL must start with https:// or http://, got {url:?}"),
+            Err(_) => None,
+        };
+
+        let manifest_cache_ttl = match std::env::var("MANIFEST_CACHE_TTL_SECS") {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|_| anyhow::anyhow!("MANIFEST_CACHE_TTL_SECS must be a number of seconds, got {value:?}"))?,
+            Err(_) => DEFAULT_MANIFEST_CACHE_TTL_SECS,
+        };
+
+        let upload_token = std::env::var("UPLOAD_TOKEN").ok().filter(|t| !t.is_empty());
+        let metrics_token = std::env::var("METRICS_TOKEN").ok().filter(|t| !t.is_empty());
+        let signing_key = std::env::var("GALLERY_SIGNING_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .map(String::into_bytes);
+
+        // Without a configured secret, cookies are only valid until the server restarts
+        let cookie_key = match std::env::var("COOKIE_SECRET") {
+            Ok(secret) => Key::try_from(secret.as_bytes())
+                .map_err(|_| anyhow::anyhow!("COOKIE_SECRET must be at least 64 bytes"))?,
+            Err(_) => {
+                tracing::warn!("COOKIE_SECRET not set; gallery logins will not survive a restart");
+                Key::generate()
+            }
+        };
+
+        let rate_limit_rps = match std::env::var("RATE_LIMIT_RPS") {
+            Ok(value) => value
+                .parse::<u32>()
+                .map_err(|_| anyhow::anyhow!("RATE_LIMIT_RPS must be a whole number of requests per second, got {value:?}"))?,
+            Err(_) => DEFAULT_RATE_LIMIT_RPS,
+        };
+        let trust_forwarded = std::env::var("RATE_LIMIT_TRUST_FORWARDED").is_ok_and(|v| v == "1" || v == "true");
+        let rate_limiter = (rate_limit_rps > 0)
+            .then(|| Arc::new(RateLimiter::new(rate_limit_rps, trust_forwarded)));
+
+        let album_index = std::env::var("ENABLE_ALBUM_INDEX").is_ok_and(|v| v == "1" || v == "true");
+        let redirect_originals = std::env::var("REDIRECT_ORIGINALS").is_ok_and(|v| v == "1" || v == "true");
+
+        Ok(Self {
+            s3,
+            public_base_url,
+            presign_expiry,
+            cdn_base_url,
+            signing_key,
+            upload_token,
+            metrics_token,
+            cookie_key,
+            manifest_cache: Arc::new(ManifestCache::new(Duration::from_secs(manifest_cache_ttl))),
+            album_locks: Arc::new(AlbumLocks::default()),
+            rate_limiter,
+            metrics: Arc::new(Metrics::default()),
+            album_index,
+            redirect_originals,
+        })
+    }
+
+    /// Presigned GET URL for an album object, served through the CDN when one is configured.
+    /// Only the origin is swapped; the CDN must forward the query string so S3 can check the signature.
+    pub async fn presigned_url(&self, key: &str) -> Result<String> {
+        let url = self.s3.generate_presigned_url(key, self.presign_expiry).await?;
+        let Some(cdn_base_url) = &self.cdn_base_url else {
+            return Ok(url);
+        };
+
+        let path_start = url
+            .find("://")
+            .and_then(|scheme_end| url[scheme_end + 3..].find('/').map(|i| scheme_end + 3 + i))
+            .unwrap_or(url.len());
+        Ok(format!("{cdn_base_url}{}", &url[path_start..]))
+    }
+}
 
-        Ok(Self { s3 })
+impl FromRef<AppState> for Key {
+    fn from_ref(state: &AppState) -> Self {
+        state.cookie_key.clone()
     }
 }